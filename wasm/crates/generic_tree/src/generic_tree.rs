@@ -189,12 +189,12 @@ impl<'bump, F: Float + Send + Sync, const N: usize, const N2: usize, D: TreeData
         }
     }
 
-    /// 使用func: Fn(&Node<F, N, D>, usize) -> bool去先序遍历每一个节点
+    /// 使用func: FnMut(&Node<F, N, D>, usize) -> bool去先序遍历每一个节点
     ///
     /// 如果func返回true，那么该节点的子节点不会被访问
-    pub fn visit_pre_order<FF>(&self, func: FF) -> ()
+    pub fn visit_pre_order<FF>(&self, mut func: FF) -> ()
     where
-        FF: Fn(&Node<'bump, F, N, N2, D>, usize) -> bool,
+        FF: FnMut(&Node<'bump, F, N, N2, D>, usize) -> bool,
     {
         let mut stack = vec![(&self.root, 0)];
         while let Some((node, depth)) = stack.pop() {
@@ -732,4 +732,38 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_from_nodes_4d() {
+        /// 6^4的网格点，data为其在网格中的编号
+        fn grid() -> impl Iterator<Item = ([f64; 4], usize)> {
+            (0..6 * 6 * 6 * 6).map(|i| {
+                let coord = [i % 6, i / 6 % 6, i / 36 % 6, i / 216].map(|x| (x * 10) as f64);
+                (coord, i)
+            })
+        }
+
+        let herd = Herd::new();
+        let nodes = grid()
+            .map(|(coord, i)| Node::new_point(coord, i))
+            .collect::<Vec<_>>();
+        let tree = GenericTree::<'_, f64, 4, 16, Data>::from_nodes(&herd, nodes, 1.0, 15);
+        tree.root.check().unwrap();
+        for (coord, i) in grid() {
+            assert_eq!(*tree.find_closest(&coord).unwrap().data(), i);
+        }
+
+        let nodes = grid()
+            .map(|(coord, i)| herd.get().alloc(Node::new_point(coord, i)))
+            .collect::<Vec<_>>();
+        let tree = GenericTree::<'_, f64, 4, 16, Data>::new_in_par(&herd, nodes, 1.0, 15);
+        tree.root.check().unwrap();
+        let mut count = 0;
+        tree.visit_post_order_mut(|node, _| {
+            if let Node::Point { .. } = node {
+                count += 1;
+            }
+        });
+        assert_eq!(count, 6 * 6 * 6 * 6);
+    }
 }
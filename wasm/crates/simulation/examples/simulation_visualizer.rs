@@ -46,16 +46,16 @@ fn build_simulation(
 ) -> Simulation<f64, 2, RandomData> {
     let mut simulation: Simulation<f64, 2, RandomData> = Simulation::from_data(data);
     let mut nbody_force: NBodyForce<f64, 2, 4, RandomData> = NBodyForce::default();
-    nbody_force.set_strength_fn(|_, _| -50_f64);
+    nbody_force.set_strength(-50_f64);
     simulation.add_force(String::from("n-body"), Box::new(nbody_force));
     let mut position_force = PositionForce::default();
-    position_force.set_strength_fn(|_, _| [Some(1f64); 2]);
+    position_force.set_strength([Some(1f64); 2]);
     simulation.add_force(String::from("position"), Box::new(position_force));
     simulation.add_force(String::from("center"), Box::new(CenterForce::default()));
-    let mut link_force = LinkForce::default();
+    let mut link_force: LinkForce<f64, 2, RandomData> = LinkForce::default();
     link_force.set_links(links);
-    link_force.set_distance_fn(|_, _| 0_f64);
-    link_force.set_strength_fn(|_, _| 1_f64);
+    link_force.set_distance(0_f64);
+    link_force.set_strength(1_f64);
     simulation.add_force(String::from("link"), Box::new(link_force));
     simulation
 }
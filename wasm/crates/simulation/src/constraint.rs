@@ -0,0 +1,130 @@
+use crate::data::PointData;
+use num::Float;
+use std::collections::HashMap;
+
+/// Geometric constraint between nodes, nodes are referred to by [PointData::index]
+///
+/// Constraints are projected after integration in every [crate::Simulation::tick], see
+/// [crate::Simulation::add_constraint]. Fixed axes never move, the other nodes of the constraint
+/// take the whole correction along them
+#[derive(Clone, Debug)]
+pub enum Constraint<F: Float, const N: usize> {
+    /// `left.coord[axis] + gap <= right.coord[axis]`
+    Separation {
+        axis: usize,
+        left: usize,
+        right: usize,
+        gap: F,
+    },
+    /// Every node has the same `coord[axis]`
+    Alignment { axis: usize, nodes: Vec<usize> },
+    /// `target.coord == source.coord + offset`
+    Offset {
+        source: usize,
+        target: usize,
+        offset: [F; N],
+    },
+}
+
+/// Weight of a node in a projection along `axis`, fixed axes do not move
+fn mobility<F: Float, const N: usize, D>(point_data: &PointData<F, N, D>, axis: usize) -> F {
+    match point_data.fixed_position[axis] {
+        Some(_) => F::zero(),
+        None => F::one(),
+    }
+}
+
+/// Split `error` between two nodes according to their mobility, `None` if neither can move
+fn split<F: Float>(error: F, a: F, b: F) -> Option<(F, F)> {
+    let total = a + b;
+    match total > F::zero() {
+        true => Some((error * a / total, error * b / total)),
+        false => None,
+    }
+}
+
+impl<F: Float, const N: usize> Constraint<F, N> {
+    /// Move the nodes the least to satisfy the constraint, constraints on unknown nodes are ignored
+    ///
+    /// `index_map` is [PointData::index_map] of `point_data`
+    pub fn project<D>(
+        &self,
+        point_data: &mut [PointData<F, N, D>],
+        index_map: &HashMap<usize, usize>,
+    ) {
+        match self {
+            &Constraint::Separation {
+                axis,
+                left,
+                right,
+                gap,
+            } => {
+                let (left, right) = match (index_map.get(&left), index_map.get(&right)) {
+                    (Some(&left), Some(&right)) if left != right => (left, right),
+                    _ => return,
+                };
+                let error = point_data[left].coord[axis] + gap - point_data[right].coord[axis];
+                if error <= F::zero() {
+                    return;
+                }
+                let (a, b) = (
+                    mobility(&point_data[left], axis),
+                    mobility(&point_data[right], axis),
+                );
+                if let Some((dl, dr)) = split(error, a, b) {
+                    point_data[left].coord[axis] = point_data[left].coord[axis] - dl;
+                    point_data[right].coord[axis] = point_data[right].coord[axis] + dr;
+                }
+            }
+            Constraint::Alignment { axis, nodes } => {
+                let positions = nodes
+                    .iter()
+                    .filter_map(|index| index_map.get(index).copied())
+                    .collect::<Vec<_>>();
+                // 有固定节点时对齐到固定节点，否则对齐到平均值
+                let fixed = positions
+                    .iter()
+                    .filter(|&&position| point_data[position].fixed_position[*axis].is_some())
+                    .collect::<Vec<_>>();
+                let anchors = match fixed.is_empty() {
+                    true => positions.iter().collect::<Vec<_>>(),
+                    false => fixed,
+                };
+                if anchors.is_empty() {
+                    return;
+                }
+                let target = anchors.iter().fold(F::zero(), |s, &&position| {
+                    s + point_data[position].coord[*axis]
+                }) / F::from(anchors.len()).unwrap();
+                for &position in positions.iter() {
+                    if point_data[position].fixed_position[*axis].is_none() {
+                        point_data[position].coord[*axis] = target;
+                    }
+                }
+            }
+            Constraint::Offset {
+                source,
+                target,
+                offset,
+            } => {
+                let (source, target) = match (index_map.get(source), index_map.get(target)) {
+                    (Some(&source), Some(&target)) if source != target => (source, target),
+                    _ => return,
+                };
+                for axis in 0..N {
+                    let (a, b) = (
+                        mobility(&point_data[source], axis),
+                        mobility(&point_data[target], axis),
+                    );
+                    let error = point_data[target].coord[axis]
+                        - point_data[source].coord[axis]
+                        - offset[axis];
+                    if let Some((ds, dt)) = split(error, a, b) {
+                        point_data[source].coord[axis] = point_data[source].coord[axis] + ds;
+                        point_data[target].coord[axis] = point_data[target].coord[axis] - dt;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::TickStats;
+use num::Float;
+
+/// Decides how alpha evolves from one tick to the next
+pub trait CoolingSchedule<F: Float> {
+    /// Compute alpha for the coming tick, `last_tick` is None before the first tick
+    fn next_alpha(
+        &mut self,
+        alpha: F,
+        alpha_target: F,
+        alpha_decay: F,
+        last_tick: Option<&TickStats<F>>,
+    ) -> F;
+
+    /// Forget any state collected so far, called by [crate::Simulation::restart]
+    fn reset(&mut self) {}
+}
+
+/// alpha approaches alpha_target by `alpha_decay` of the remaining distance every tick (d3 default)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExponentialCooling;
+
+impl<F: Float> CoolingSchedule<F> for ExponentialCooling {
+    fn next_alpha(
+        &mut self,
+        alpha: F,
+        alpha_target: F,
+        alpha_decay: F,
+        _: Option<&TickStats<F>>,
+    ) -> F {
+        alpha + (alpha_target - alpha) * alpha_decay
+    }
+}
+
+/// alpha approaches alpha_target by a constant `step` every tick
+#[derive(Clone, Copy, Debug)]
+pub struct LinearCooling<F: Float> {
+    pub step: F,
+}
+
+impl<F: Float> LinearCooling<F> {
+    pub fn new(step: F) -> LinearCooling<F> {
+        LinearCooling { step }
+    }
+}
+
+impl<F: Float> Default for LinearCooling<F> {
+    fn default() -> Self {
+        LinearCooling {
+            step: F::from(1.0 / 300.0).unwrap(),
+        }
+    }
+}
+
+impl<F: Float> CoolingSchedule<F> for LinearCooling<F> {
+    fn next_alpha(&mut self, alpha: F, alpha_target: F, _: F, _: Option<&TickStats<F>>) -> F {
+        if alpha > alpha_target {
+            (alpha - self.step).max(alpha_target)
+        } else {
+            (alpha + self.step).min(alpha_target)
+        }
+    }
+}
+
+/// Exponential cooling whose rate follows the kinetic energy, after Yifan Hu's adaptive step length:
+/// the rate slows down by `ratio` after `patience` ticks of falling energy and speeds up by `1 / ratio`
+/// as soon as the energy rises
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveCooling<F: Float> {
+    pub ratio: F,
+    pub patience: usize,
+    scale: F,
+    progress: usize,
+    last_energy: Option<F>,
+}
+
+impl<F: Float> AdaptiveCooling<F> {
+    pub fn new(ratio: F, patience: usize) -> AdaptiveCooling<F> {
+        AdaptiveCooling {
+            ratio,
+            patience,
+            scale: F::one(),
+            progress: 0,
+            last_energy: None,
+        }
+    }
+}
+
+impl<F: Float> Default for AdaptiveCooling<F> {
+    fn default() -> Self {
+        AdaptiveCooling::new(F::from(0.9).unwrap(), 5)
+    }
+}
+
+impl<F: Float> CoolingSchedule<F> for AdaptiveCooling<F> {
+    fn next_alpha(
+        &mut self,
+        alpha: F,
+        alpha_target: F,
+        alpha_decay: F,
+        last_tick: Option<&TickStats<F>>,
+    ) -> F {
+        if let Some(stats) = last_tick {
+            match self.last_energy {
+                Some(last_energy) if stats.kinetic_energy < last_energy => {
+                    self.progress += 1;
+                    if self.progress >= self.patience {
+                        self.progress = 0;
+                        self.scale = self.scale * self.ratio;
+                    }
+                }
+                Some(_) => {
+                    self.progress = 0;
+                    self.scale = self.scale / self.ratio;
+                }
+                None => {}
+            }
+            self.last_energy = Some(stats.kinetic_energy);
+        }
+        let decay = (alpha_decay * self.scale).min(F::one());
+        alpha + (alpha_target - alpha) * decay
+    }
+
+    fn reset(&mut self) {
+        self.scale = F::one();
+        self.progress = 0;
+        self.last_energy = None;
+    }
+}
@@ -1,50 +1,54 @@
-use crate::data::PointData;
-use num::Float;
+use std::collections::HashMap;
 
-pub struct LinkData<F: Float, const N: usize, D> {
+pub struct LinkData<L = ()> {
     pub index: usize,
-    source: *const PointData<F, N, D>,
-    target: *const PointData<F, N, D>,
+    /// 端点id，即[crate::data::PointData::index]
+    pub source: usize,
+    pub target: usize,
+    /// 端点在point_data中的下标，point_data顺序变化后需要重新构建
+    pub source_position: usize,
+    pub target_position: usize,
+    data: L,
 }
 
-impl<F: Float, const N: usize, D> LinkData<F, N, D> {
+impl<L> LinkData<L> {
     pub fn new(
         index: usize,
-        source: &mut PointData<F, N, D>,
-        target: &mut PointData<F, N, D>,
-    ) -> LinkData<F, N, D> {
+        (source, target): (usize, usize),
+        (source_position, target_position): (usize, usize),
+        data: L,
+    ) -> LinkData<L> {
         LinkData {
             index,
             source,
             target,
+            source_position,
+            target_position,
+            data,
         }
     }
 
-    // TODO 更好的from方案
+    /// `payloads` is parallel to `pairs`, `index_map` is [crate::data::PointData::index_map] of the nodes
     pub fn from_pairs(
         pairs: &[(usize, usize)],
-        point_data: &[PointData<F, N, D>],
-    ) -> Vec<LinkData<F, N, D>> {
+        payloads: &[L],
+        index_map: &HashMap<usize, usize>,
+    ) -> Vec<LinkData<L>>
+    where
+        L: Clone,
+    {
         let mut link_data = Vec::with_capacity(pairs.len());
-        // TODO 先构建mapping？
-        for (index, (s, t)) in pairs.iter().enumerate() {
-            // TODO unwrap可能出错
-            let source = point_data.iter().find(|&d| d.index == *s).unwrap();
-            let target = point_data.iter().find(|&d| d.index == *t).unwrap();
-            link_data.push(LinkData {
-                index,
-                source,
-                target,
-            })
+        for (index, (&(s, t), data)) in pairs.iter().zip(payloads).enumerate() {
+            // 端点不存在（尚未加入或已被移除）的边不参与模拟，index仍与pairs对应
+            if let (Some(&source), Some(&target)) = (index_map.get(&s), index_map.get(&t)) {
+                link_data.push(LinkData::new(index, (s, t), (source, target), data.clone()))
+            }
         }
         link_data
     }
 
-    pub fn source(&self) -> &PointData<F, N, D> {
-        unsafe { &(*self.source) }
-    }
-
-    pub fn target(&self) -> &PointData<F, N, D> {
-        unsafe { &(*self.target) }
+    /// User payload of the link
+    pub fn data(&self) -> &L {
+        &self.data
     }
 }
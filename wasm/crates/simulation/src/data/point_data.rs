@@ -1,4 +1,5 @@
 use num::Float;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 
 #[derive(Clone)]
@@ -9,7 +10,10 @@ pub struct PointData<F: Float, const N: usize, D> {
     pub velocity: [F; N],
     pub strength: F,
     pub radius: F,
-    pub fixed_position: Option<[F; N]>,
+    /// 每个轴可以单独固定（如d3的fx/fy），固定的轴每次tick都会被复位
+    pub fixed_position: [Option<F>; N],
+    /// Soft anchor: target coordinate and spring strength, pulls the node every tick
+    pub anchor: Option<([F; N], F)>,
 }
 
 impl<F: Float, const N: usize, D> PointData<F, N, D> {
@@ -21,7 +25,8 @@ impl<F: Float, const N: usize, D> PointData<F, N, D> {
             velocity: [F::zero(); N],
             strength: F::zero(),
             radius: F::zero(),
-            fixed_position: None,
+            fixed_position: [None; N],
+            anchor: None,
         }
     }
 
@@ -32,6 +37,31 @@ impl<F: Float, const N: usize, D> PointData<F, N, D> {
     pub fn coord_mut(&mut self) -> &mut [F; N] {
         &mut self.coord
     }
+
+    /// True if any axis is fixed
+    pub fn is_fixed(&self) -> bool {
+        self.fixed_position.iter().any(Option::is_some)
+    }
+
+    /// Fix every axis at `coord`
+    pub fn pin(&mut self, coord: [F; N]) {
+        self.fixed_position = coord.map(Some);
+    }
+
+    /// Release every fixed axis and the anchor
+    pub fn unpin(&mut self) {
+        self.fixed_position = [None; N];
+        self.anchor = None;
+    }
+
+    /// Map every [PointData::index] to its position in `point_data`
+    pub fn index_map(point_data: &[PointData<F, N, D>]) -> HashMap<usize, usize> {
+        point_data
+            .iter()
+            .enumerate()
+            .map(|(position, point_data)| (point_data.index, position))
+            .collect()
+    }
 }
 
 impl<F: Float, const N: usize, D: Default> Default for PointData<F, N, D> {
@@ -43,7 +73,8 @@ impl<F: Float, const N: usize, D: Default> Default for PointData<F, N, D> {
             velocity: [F::zero(); N],
             strength: F::zero(),
             radius: F::zero(),
-            fixed_position: None,
+            fixed_position: [None; N],
+            anchor: None,
         }
     }
 }
@@ -63,12 +94,10 @@ impl<F: Float, const N: usize, D: Display> Display for PointData<F, N, D> {
                 .iter()
                 .map(|point| point.to_f64().unwrap())
                 .collect::<Vec<_>>(),
-            match self.fixed_position {
-                None => None,
-                Some(position) => Some(position.iter()
-                    .map(|point| point.to_f64().unwrap())
-                    .collect::<Vec<_>>())
-            }
+            self.fixed_position
+                .iter()
+                .map(|axis| axis.map(|point| point.to_f64().unwrap()))
+                .collect::<Vec<_>>()
         )
     }
 }
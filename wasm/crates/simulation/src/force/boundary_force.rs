@@ -0,0 +1,334 @@
+use crate::data::PointData;
+use crate::force::{ForceContext, ForceSimulate, TickContext};
+use num::Float;
+use std::marker::PhantomData;
+
+const POLYGON_PROJECTIONS: usize = 8;
+
+/// Region the nodes are kept in, see [BoundaryForce] and [crate::Simulation::set_boundary]
+#[derive(Clone, Debug)]
+pub enum Boundary<F: Float, const N: usize> {
+    /// Axis-aligned box
+    Box { min: [F; N], max: [F; N] },
+    /// Circle in 2-D, sphere in 3-D
+    Sphere { center: [F; N], radius: F },
+    /// Simple polygon on the plane of the first two axes, the other axes are not bounded
+    Polygon(Vec<[F; 2]>),
+}
+
+impl<F: Float, const N: usize> Boundary<F, N> {
+    /// Closest position to `coord` keeping a node of `radius` inside, None if it already is
+    pub fn contain(&self, coord: &[F; N], radius: F) -> Option<[F; N]> {
+        match self {
+            Boundary::Box { min, max } => {
+                let mut inside = *coord;
+                let two = F::from(2).unwrap();
+                for i in 0..N {
+                    let (low, high) = (min[i] + radius, max[i] - radius);
+                    // 盒子比节点还小时放在中间
+                    inside[i] = match low > high {
+                        true => (min[i] + max[i]) / two,
+                        false => coord[i].max(low).min(high),
+                    };
+                }
+                (inside != *coord).then_some(inside)
+            }
+            Boundary::Sphere { center, radius: r } => {
+                let limit = (*r - radius).max(F::zero());
+                let distance = (0..N)
+                    .map(|i| coord[i] - center[i])
+                    .fold(F::zero(), |s, x| s + x * x)
+                    .sqrt();
+                if distance <= limit {
+                    return None;
+                }
+                let mut inside = *center;
+                for i in 0..N {
+                    inside[i] = center[i] + (coord[i] - center[i]) * limit / distance;
+                }
+                Some(inside)
+            }
+            Boundary::Polygon(vertices) => {
+                assert!(N >= 2, "polygon boundary needs at least 2 dimensions");
+                // 靠近顶点时推离一条边可能又靠近另一条边，交替投影几次
+                let mut moved = *coord;
+                for _ in 0..POLYGON_PROJECTIONS {
+                    let p = (moved[0], moved[1]);
+                    let (nearest, distance) = nearest_on_polygon(vertices, p)?;
+                    let inside = contains(vertices, p);
+                    if inside && distance >= radius {
+                        break;
+                    }
+                    // 沿指向多边形内部的法线，把节点放在离边界radius处
+                    let (nx, ny) = match (inside, distance > F::zero()) {
+                        (_, false) => break,
+                        (true, true) => {
+                            ((p.0 - nearest.0) / distance, (p.1 - nearest.1) / distance)
+                        }
+                        (false, true) => {
+                            ((nearest.0 - p.0) / distance, (nearest.1 - p.1) / distance)
+                        }
+                    };
+                    moved[0] = nearest.0 + nx * radius;
+                    moved[1] = nearest.1 + ny * radius;
+                }
+                (moved != *coord).then_some(moved)
+            }
+        }
+    }
+
+    /// Move the node back inside and drop (Clamp) or reflect (Bounce) its velocity pointing
+    /// outside, fixed axes are never moved
+    pub fn project<D>(&self, point_data: &mut PointData<F, N, D>, mode: BoundaryMode<F>) {
+        let inside = match self.contain(&point_data.coord, point_data.radius) {
+            Some(inside) => inside,
+            None => return,
+        };
+        let restitution = match mode {
+            BoundaryMode::Clamp => F::zero(),
+            BoundaryMode::Bounce { restitution } => restitution,
+        };
+        // 越界部分，反弹时按restitution反射回边界内
+        let mut overshoot = [F::zero(); N];
+        let mut target = inside;
+        for i in 0..N {
+            overshoot[i] = point_data.coord[i] - inside[i];
+            target[i] = inside[i] - overshoot[i] * restitution;
+        }
+        // 反射后可能越过另一侧的边界
+        let target = self.contain(&target, point_data.radius).unwrap_or(target);
+        let length = overshoot.iter().fold(F::zero(), |s, &x| s + x * x).sqrt();
+        let outward =
+            (0..N).fold(F::zero(), |s, i| s + point_data.velocity[i] * overshoot[i]) / length;
+        for i in 0..N {
+            if point_data.fixed_position[i].is_some() {
+                continue;
+            }
+            point_data.coord[i] = target[i];
+            if outward > F::zero() {
+                point_data.velocity[i] = point_data.velocity[i]
+                    - overshoot[i] / length * outward * (F::one() + restitution);
+            }
+        }
+    }
+}
+
+/// Even-odd rule
+fn contains<F: Float>(vertices: &[[F; 2]], (x, y): (F, F)) -> bool {
+    let mut inside = false;
+    for (k, a) in vertices.iter().enumerate() {
+        let b = vertices[(k + 1) % vertices.len()];
+        if (a[1] > y) != (b[1] > y) && x < a[0] + (y - a[1]) * (b[0] - a[0]) / (b[1] - a[1]) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Closest point on the edges of the polygon and its distance, None for an empty polygon
+fn nearest_on_polygon<F: Float>(vertices: &[[F; 2]], (x, y): (F, F)) -> Option<((F, F), F)> {
+    let mut nearest: Option<((F, F), F)> = None;
+    for (k, a) in vertices.iter().enumerate() {
+        let b = vertices[(k + 1) % vertices.len()];
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let length = dx * dx + dy * dy;
+        let t = match length > F::zero() {
+            true => (((x - a[0]) * dx + (y - a[1]) * dy) / length)
+                .max(F::zero())
+                .min(F::one()),
+            false => F::zero(),
+        };
+        let point = (a[0] + dx * t, a[1] + dy * t);
+        let distance = (x - point.0).hypot(y - point.1);
+        let closer = match nearest {
+            None => true,
+            Some((_, d)) => distance < d,
+        };
+        if closer {
+            nearest = Some((point, distance));
+        }
+    }
+    nearest
+}
+
+/// How [crate::Simulation::set_boundary] treats nodes leaving the boundary
+#[derive(Clone, Copy, Debug)]
+pub enum BoundaryMode<F: Float> {
+    /// Stop the node on the boundary
+    Clamp,
+    /// Reflect the part of the step crossing the boundary, `restitution` 1 keeps the whole step
+    Bounce { restitution: F },
+}
+
+/// Spring pulling the nodes outside a [Boundary] back, taking [PointData::radius] into account
+///
+/// The spring is scaled by alpha so nodes may stay outside for a while, use
+/// [crate::Simulation::set_boundary] to keep them strictly inside
+pub struct BoundaryForce<F: Float, const N: usize, D> {
+    pub boundary: Boundary<F, N>,
+    pub strength: F,
+    _point_data: PhantomData<fn(&PointData<F, N, D>)>,
+}
+
+impl<F: Float, const N: usize, D> BoundaryForce<F, N, D> {
+    pub fn new(boundary: Boundary<F, N>, strength: F) -> BoundaryForce<F, N, D> {
+        BoundaryForce {
+            boundary,
+            strength,
+            _point_data: PhantomData,
+        }
+    }
+
+    pub fn set_boundary(&mut self, boundary: Boundary<F, N>) {
+        self.boundary = boundary;
+    }
+
+    pub fn set_strength(&mut self, strength: F) {
+        self.strength = strength;
+    }
+}
+
+impl<F: Float + 'static, const N: usize, D: 'static> ForceSimulate<F, N, D>
+    for BoundaryForce<F, N, D>
+{
+    fn init(&mut self, _: &ForceContext<F, N, D>) {}
+
+    fn force(&mut self, context: &mut TickContext<F, N, D>) {
+        let k = self.strength * context.alpha;
+        for point_data in context.point_data.iter_mut() {
+            if let Some(inside) = self.boundary.contain(&point_data.coord, point_data.radius) {
+                for i in 0..N {
+                    point_data.velocity[i] =
+                        point_data.velocity[i] + (inside[i] - point_data.coord[i]) * k;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Boundary, BoundaryForce, BoundaryMode};
+    use crate::constraint::Constraint;
+    use crate::force::{NBodyForce, PositionForce};
+    use crate::integrator::{AdaptiveIntegrator, EulerIntegrator, Integrator, VerletIntegrator};
+    use crate::Simulation;
+
+    #[test]
+    fn test_boundary_force() {
+        fn settle(
+            boundary: Boundary<f64, 2>,
+            mode: BoundaryMode<f64>,
+            integrator: Box<dyn Integrator<f64, 2, i32>>,
+        ) {
+            let mut simulation: Simulation<f64, 2, i32> =
+                Simulation::from_data(Vec::from_iter(0..50));
+            for point_data in simulation.force_point_data.iter_mut() {
+                point_data.radius = 2.0;
+            }
+            simulation.add_force(
+                String::from("n-body"),
+                Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+            );
+            // 持续把节点拉向边界外
+            let mut position_force = PositionForce::default();
+            position_force.set_target_position([Some(0.0), Some(200.0)]);
+            position_force.set_strength([Some(0.05), Some(0.05)]);
+            simulation.add_force(String::from("position"), Box::new(position_force));
+            simulation.set_integrator(integrator);
+            simulation.set_boundary(boundary.clone(), mode);
+            // 起始时在边界外的节点一次tick即被移回边界内
+            for _ in 0..300 {
+                simulation.tick();
+                for point_data in simulation.force_point_data.iter() {
+                    assert!(
+                        boundary.contain(&point_data.coord, 2.0 - 1e-9).is_none(),
+                        "{:?} {:?} {:?}",
+                        mode,
+                        boundary,
+                        point_data.coord
+                    );
+                }
+            }
+        }
+
+        let square = Boundary::Box {
+            min: [-80.0, -80.0],
+            max: [80.0, 80.0],
+        };
+        let circle = Boundary::Sphere {
+            center: [0.0, 0.0],
+            radius: 80.0,
+        };
+        let diamond = Boundary::Polygon(vec![[80.0, 0.0], [0.0, 80.0], [-80.0, 0.0], [0.0, -80.0]]);
+        // 开口向上的U形，原点附近的节点起始时在缺口中，即边界外
+        let notched = Boundary::Polygon(vec![
+            [-80.0, -80.0],
+            [80.0, -80.0],
+            [80.0, 80.0],
+            [40.0, 80.0],
+            [40.0, -40.0],
+            [-40.0, -40.0],
+            [-40.0, 80.0],
+            [-80.0, 80.0],
+        ]);
+        for boundary in [square.clone(), circle.clone(), diamond, notched] {
+            settle(
+                boundary.clone(),
+                BoundaryMode::Clamp,
+                Box::new(EulerIntegrator),
+            );
+            settle(
+                boundary.clone(),
+                BoundaryMode::Clamp,
+                Box::<VerletIntegrator<f64, 2>>::default(),
+            );
+            settle(
+                boundary.clone(),
+                BoundaryMode::Clamp,
+                Box::<AdaptiveIntegrator<f64, 2>>::default(),
+            );
+            settle(
+                boundary.clone(),
+                BoundaryMode::Bounce { restitution: 0.5 },
+                Box::new(EulerIntegrator),
+            );
+        }
+
+        // 约束无法满足时仍不越界
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(Vec::from_iter(0..2));
+        simulation.add_constraint(Constraint::Offset {
+            source: 0,
+            target: 1,
+            offset: [200.0, 0.0],
+        });
+        simulation.set_boundary(square.clone(), BoundaryMode::Clamp);
+        simulation.tick();
+        for point_data in simulation.force_point_data.iter() {
+            assert!(square.contain(&point_data.coord, 0.0).is_none());
+        }
+
+        // 弹簧允许越界，但比没有边界时更紧凑
+        let spread = |strength: f64| {
+            let mut simulation: Simulation<f64, 2, i32> =
+                Simulation::from_data(Vec::from_iter(0..50));
+            simulation.add_force(
+                String::from("n-body"),
+                Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+            );
+            simulation.add_force(
+                String::from("boundary"),
+                Box::new(BoundaryForce::new(circle.clone(), strength)),
+            );
+            for _ in 0..300 {
+                simulation.tick();
+            }
+            simulation
+                .force_point_data
+                .iter()
+                .map(|point_data| point_data.coord[0].hypot(point_data.coord[1]))
+                .fold(0.0, f64::max)
+        };
+        assert!(spread(0.5) < spread(0.0));
+    }
+}
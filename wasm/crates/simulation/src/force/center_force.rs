@@ -1,5 +1,4 @@
-use crate::data::PointData;
-use crate::force::ForceSimulate;
+use crate::force::{ForceContext, ForceSimulate, TickContext};
 use num::Float;
 
 pub struct CenterForce<F: Float, const N: usize> {
@@ -33,10 +32,11 @@ impl<F: Float, const N: usize> Default for CenterForce<F, N> {
     }
 }
 
-impl<F: Float, const N: usize, D> ForceSimulate<F, N, D> for CenterForce<F, N> {
-    fn init(&mut self, _: &[PointData<F, N, D>]) {}
+impl<F: Float + 'static, const N: usize, D> ForceSimulate<F, N, D> for CenterForce<F, N> {
+    fn init(&mut self, _: &ForceContext<F, N, D>) {}
 
-    fn force(&self, force_point_data: &mut [PointData<F, N, D>], _: F) {
+    fn force(&mut self, context: &mut TickContext<F, N, D>) {
+        let force_point_data = &mut *context.point_data;
         let n = F::from(force_point_data.len() as f64).unwrap();
         let mut s = [F::zero(); N];
         for point_data in force_point_data.iter_mut() {
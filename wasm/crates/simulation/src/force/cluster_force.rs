@@ -0,0 +1,305 @@
+use crate::data::PointData;
+use crate::force::utils::{about_zero, jiggle};
+use crate::force::{ForceContext, ForceSimulate, NodeFn, TickContext};
+use num::Float;
+use std::collections::HashMap;
+
+/// Pulls every node toward the centroid of its group, the centroids are recomputed every tick
+///
+/// With a positive `centroid_repulsion` the groups also push each other apart, every group acting
+/// as a charge of its member count placed at its centroid
+pub struct ClusterForce<F: Float, const N: usize, D> {
+    pub group_fn: NodeFn<F, N, D, usize>,
+    pub strength_fn: NodeFn<F, N, D, F>,
+    /// 组中心之间的斥力强度，为0时组之间不排斥
+    pub centroid_repulsion: F,
+    /// 节点id -> 其组在centroids中的下标，组id可以是任意值
+    slots: Vec<usize>,
+    strengths: Vec<F>,
+    /// 不同组的数量
+    group_count: usize,
+    /// 每个组本次tick的坐标和与节点数，每次tick复用
+    centroids: Vec<([F; N], usize)>,
+    /// 每个组本次tick因组间斥力获得的速度变化
+    shifts: Vec<[F; N]>,
+    /// 访问器在init之后被修改，需要重新init
+    stale: bool,
+}
+
+impl<F: Float + Send + Sync + 'static, const N: usize, D: 'static> ClusterForce<F, N, D> {
+    pub fn new(
+        group_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> usize + Send + Sync + 'static,
+        strength_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F + Send + Sync + 'static,
+        centroid_repulsion: F,
+    ) -> ClusterForce<F, N, D> {
+        ClusterForce {
+            group_fn: Box::new(group_fn),
+            strength_fn: Box::new(strength_fn),
+            centroid_repulsion,
+            slots: Vec::new(),
+            strengths: Vec::new(),
+            group_count: 0,
+            centroids: Vec::new(),
+            shifts: Vec::new(),
+            stale: false,
+        }
+    }
+
+    pub fn set_group_fn(
+        &mut self,
+        group_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> usize + Send + Sync + 'static,
+    ) {
+        self.group_fn = Box::new(group_fn);
+        self.stale = true;
+    }
+
+    /// Group of every node, indexed by [PointData::index], nodes past the end (added later) go to
+    /// the default group
+    pub fn set_groups(&mut self, groups: Vec<usize>) {
+        self.set_group_fn(move |point_data, nodes| {
+            groups
+                .get(point_data.index)
+                .copied()
+                .unwrap_or_else(|| default_group_fn(point_data, nodes))
+        });
+    }
+
+    pub fn set_strength_fn(
+        &mut self,
+        strength_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F + Send + Sync + 'static,
+    ) {
+        self.strength_fn = Box::new(strength_fn);
+        self.stale = true;
+    }
+
+    /// Same strength for every node
+    pub fn set_strength(&mut self, strength: F) {
+        self.set_strength_fn(move |_, _| strength);
+    }
+
+    /// Strength of every node, indexed by [PointData::index], nodes past the end get the default
+    /// strength
+    pub fn set_strengths(&mut self, strengths: Vec<F>) {
+        self.set_strength_fn(move |point_data, nodes| {
+            strengths
+                .get(point_data.index)
+                .copied()
+                .unwrap_or_else(|| default_strength_fn(point_data, nodes))
+        });
+    }
+
+    pub fn set_centroid_repulsion(&mut self, centroid_repulsion: F) {
+        self.centroid_repulsion = centroid_repulsion;
+    }
+}
+
+fn default_group_fn<F: Float, const N: usize, D>(
+    _: &PointData<F, N, D>,
+    _: &[PointData<F, N, D>],
+) -> usize {
+    0
+}
+
+fn default_strength_fn<F: Float, const N: usize, D>(
+    _: &PointData<F, N, D>,
+    _: &[PointData<F, N, D>],
+) -> F {
+    F::from(0.1_f64).unwrap()
+}
+
+impl<F: Float + 'static, const N: usize, D: 'static> Default for ClusterForce<F, N, D> {
+    fn default() -> Self {
+        ClusterForce {
+            group_fn: Box::new(default_group_fn),
+            strength_fn: Box::new(default_strength_fn),
+            centroid_repulsion: F::zero(),
+            slots: Vec::new(),
+            strengths: Vec::new(),
+            group_count: 0,
+            centroids: Vec::new(),
+            shifts: Vec::new(),
+            stale: false,
+        }
+    }
+}
+
+impl<F: Float + Send + Sync + 'static, const N: usize, D: 'static> ForceSimulate<F, N, D>
+    for ClusterForce<F, N, D>
+{
+    fn init(&mut self, context: &ForceContext<F, N, D>) {
+        let force_point_data = context.point_data();
+        self.slots = vec![0; context.index_len()];
+        self.strengths = vec![F::zero(); context.index_len()];
+        let mut slots: HashMap<usize, usize> = HashMap::new();
+        for point_data in force_point_data {
+            let group = (self.group_fn)(point_data, force_point_data);
+            let next = slots.len();
+            self.slots[point_data.index] = *slots.entry(group).or_insert(next);
+            self.strengths[point_data.index] = (self.strength_fn)(point_data, force_point_data);
+        }
+        self.group_count = slots.len();
+        self.stale = false;
+    }
+
+    fn force(&mut self, context: &mut TickContext<F, N, D>) {
+        let alpha = context.alpha;
+        self.centroids.clear();
+        self.centroids.resize(self.group_count, ([F::zero(); N], 0));
+        for point_data in context.point_data.iter() {
+            let (sum, count) = &mut self.centroids[self.slots[point_data.index]];
+            for i in 0..N {
+                sum[i] = sum[i] + point_data.coord[i];
+            }
+            *count += 1;
+        }
+        for (sum, count) in self.centroids.iter_mut() {
+            if *count > 0 {
+                let count = F::from(*count).unwrap();
+                for x in sum.iter_mut() {
+                    *x = *x / count;
+                }
+            }
+        }
+
+        self.shifts.clear();
+        self.shifts.resize(self.group_count, [F::zero(); N]);
+        if !about_zero(self.centroid_repulsion) {
+            // 节点被移除后可能留下空组
+            let occupied = (0..self.group_count)
+                .filter(|&slot| self.centroids[slot].1 > 0)
+                .collect::<Vec<_>>();
+            for (k, &a) in occupied.iter().enumerate() {
+                for &b in occupied[k + 1..].iter() {
+                    let ((ca, na), (cb, nb)) = (self.centroids[a], self.centroids[b]);
+                    let mut d = [F::zero(); N];
+                    for i in 0..N {
+                        d[i] = ca[i] - cb[i];
+                        if about_zero(d[i]) {
+                            d[i] = jiggle(context.rng);
+                        }
+                    }
+                    let l = d.iter().fold(F::zero(), |s, &x| s + x * x);
+                    let k = self.centroid_repulsion * alpha / l;
+                    let (na, nb) = (F::from(na).unwrap(), F::from(nb).unwrap());
+                    for i in 0..N {
+                        self.shifts[a][i] = self.shifts[a][i] + d[i] * k * nb;
+                        self.shifts[b][i] = self.shifts[b][i] - d[i] * k * na;
+                    }
+                }
+            }
+        }
+
+        for point_data in context.point_data.iter_mut() {
+            let slot = self.slots[point_data.index];
+            let k = self.strengths[point_data.index] * alpha;
+            let (centroid, _) = self.centroids[slot];
+            for i in 0..N {
+                point_data.velocity[i] = point_data.velocity[i]
+                    + (centroid[i] - point_data.coord[i]) * k
+                    + self.shifts[slot][i];
+            }
+        }
+    }
+
+    fn needs_init(&self) -> bool {
+        self.stale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClusterForce;
+    use crate::force::NBodyForce;
+    use crate::Simulation;
+
+    #[test]
+    fn test_cluster_force() {
+        /// 按data奇偶分为两组，返回两组的中心和各节点到本组中心的最大距离
+        fn settle(centroid_repulsion: f64) -> ([[f64; 2]; 2], f64) {
+            let mut simulation: Simulation<f64, 2, i32> =
+                Simulation::from_data(Vec::from_iter(0..40));
+            simulation.add_force(
+                String::from("n-body"),
+                Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+            );
+            simulation.add_force(
+                String::from("cluster"),
+                Box::new(ClusterForce::new(
+                    |point_data, _| (point_data.data % 2) as usize,
+                    |_, _| 0.5,
+                    centroid_repulsion,
+                )),
+            );
+            for _ in 0..300 {
+                simulation.tick();
+            }
+            let mut centroids = [[0.0; 2]; 2];
+            for point_data in simulation.force_point_data.iter() {
+                let centroid = &mut centroids[(point_data.data % 2) as usize];
+                centroid[0] += point_data.coord[0] / 20.0;
+                centroid[1] += point_data.coord[1] / 20.0;
+            }
+            let spread = simulation
+                .force_point_data
+                .iter()
+                .map(|point_data| {
+                    let [x, y] = centroids[(point_data.data % 2) as usize];
+                    (point_data.coord[0] - x).hypot(point_data.coord[1] - y)
+                })
+                .fold(0.0, f64::max);
+            (centroids, spread)
+        }
+        let distance = |[a, b]: [[f64; 2]; 2]| (a[0] - b[0]).hypot(a[1] - b[1]);
+
+        let (centroids, _) = settle(0.0);
+        let (repelled, spread) = settle(2000.0);
+        // 组间斥力使两组分开，两组节点不再相互重叠
+        assert!(distance(repelled) > distance(centroids));
+        assert!(distance(repelled) > 2.0 * spread);
+    }
+
+    #[test]
+    fn test_cluster_force_sparse_group_ids() {
+        let settle = |groups: [usize; 2]| {
+            let mut simulation: Simulation<f64, 2, i32> = Simulation::with_seed(0);
+            simulation.set_data(Vec::from_iter(0..40));
+            simulation.add_force(
+                String::from("cluster"),
+                Box::new(ClusterForce::new(
+                    move |point_data, _| groups[(point_data.data % 2) as usize],
+                    |_, _| 0.5,
+                    2000.0,
+                )),
+            );
+            for _ in 0..50 {
+                simulation.tick();
+            }
+            simulation
+                .force_point_data
+                .iter()
+                .map(|point_data| point_data.coord)
+                .collect::<Vec<_>>()
+        };
+        // 组id只用于区分组，不按下标分配空间
+        assert_eq!(settle([usize::MAX, 1 << 40]), settle([0, 1]));
+    }
+
+    #[test]
+    fn test_cluster_force_groups_after_add_nodes() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(vec![0, 1, 2, 3]);
+        let mut cluster_force = ClusterForce::default();
+        cluster_force.set_groups(vec![0, 0, 1, 1]);
+        cluster_force.set_strengths(vec![0.5; 4]);
+        simulation.add_force(String::from("cluster"), Box::new(cluster_force));
+        simulation.tick();
+
+        // 新节点超出按下标给定的组，归入默认组
+        simulation.add_nodes(vec![4, 5]);
+        for _ in 0..10 {
+            simulation.tick();
+        }
+        for point_data in simulation.force_point_data.iter() {
+            assert!(point_data.coord.iter().all(|x| x.is_finite()));
+        }
+    }
+}
@@ -1,7 +1,116 @@
 use crate::data::PointData;
+use crate::force::utils::index_len;
 use num::Float;
+use rand::rngs::StdRng;
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::HashMap;
 
-pub trait ForceSimulate<F: Float, const N: usize, D> {
-    fn init(&mut self, force_point_data: &[PointData<F, N, D>]);
-    fn force(&self, force_point_data: &mut [PointData<F, N, D>], alpha: F);
+/// Per-node accessor, called with a node and all nodes, the results are cached by [PointData::index]
+pub type NodeFn<F, const N: usize, D, T> =
+    Box<dyn Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> T + Send + Sync>;
+
+/// Read-only view of the nodes handed to [ForceSimulate::init]
+///
+/// Only valid during the call, forces copy whatever they need instead of keeping references
+pub struct ForceContext<'a, F: Float, const N: usize, D> {
+    point_data: &'a [PointData<F, N, D>],
+    /// 节点id -> 节点在point_data中的下标
+    index_map: Cow<'a, HashMap<usize, usize>>,
+}
+
+impl<'a, F: Float, const N: usize, D> ForceContext<'a, F, N, D> {
+    /// `index_map` must be [PointData::index_map] of `point_data`
+    pub fn new(
+        point_data: &'a [PointData<F, N, D>],
+        index_map: &'a HashMap<usize, usize>,
+    ) -> ForceContext<'a, F, N, D> {
+        ForceContext {
+            point_data,
+            index_map: Cow::Borrowed(index_map),
+        }
+    }
+
+    /// Build the id mapping on the fly, for using a force outside of a [crate::Simulation]
+    pub fn from_point_data(point_data: &'a [PointData<F, N, D>]) -> ForceContext<'a, F, N, D> {
+        ForceContext {
+            point_data,
+            index_map: Cow::Owned(PointData::index_map(point_data)),
+        }
+    }
+
+    pub fn point_data(&self) -> &'a [PointData<F, N, D>] {
+        self.point_data
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.point_data.len()
+    }
+
+    /// Ids of every node, in the order of [ForceContext::point_data]
+    pub fn ids(&self) -> impl Iterator<Item = usize> + 'a {
+        self.point_data.iter().map(|point_data| point_data.index)
+    }
+
+    /// Length of a buffer indexed by node id
+    pub fn index_len(&self) -> usize {
+        index_len(self.point_data)
+    }
+
+    pub fn index_map(&self) -> &HashMap<usize, usize> {
+        &self.index_map
+    }
+
+    /// Position of the node with id `index` in [ForceContext::point_data]
+    pub fn position_of(&self, index: usize) -> Option<usize> {
+        self.index_map.get(&index).copied()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&'a PointData<F, N, D>> {
+        self.position_of(index)
+            .map(|position| &self.point_data[position])
+    }
+
+    /// User data of the node with id `index`
+    pub fn data(&self, index: usize) -> Option<&'a D> {
+        self.get(index).map(|point_data| &point_data.data)
+    }
+}
+
+/// Everything a force may touch during one [crate::Simulation::tick]
+pub struct TickContext<'a, F: Float, const N: usize, D> {
+    pub point_data: &'a mut [PointData<F, N, D>],
+    pub alpha: F,
+    /// `rng` is owned by the [crate::Simulation], every random decision must be drawn from it
+    /// to keep the layout reproducible
+    pub rng: &'a mut StdRng,
+}
+
+impl<'a, F: Float, const N: usize, D> TickContext<'a, F, N, D> {
+    pub fn new(
+        point_data: &'a mut [PointData<F, N, D>],
+        alpha: F,
+        rng: &'a mut StdRng,
+    ) -> TickContext<'a, F, N, D> {
+        TickContext {
+            point_data,
+            alpha,
+            rng,
+        }
+    }
+}
+
+/// `Any` lets [crate::Simulation::get_force] hand back the concrete force
+pub trait ForceSimulate<F: Float, const N: usize, D>: Any {
+    /// Called whenever the nodes change, everything derived from them is rebuilt here
+    fn init(&mut self, context: &ForceContext<F, N, D>);
+
+    /// Forces may keep scratch buffers and warm-start data across ticks in `self`
+    fn force(&mut self, context: &mut TickContext<F, N, D>);
+
+    /// True once a setter invalidated what [ForceSimulate::init] computed,
+    /// [crate::Simulation] then re-initialises the force before the next tick
+    fn needs_init(&self) -> bool {
+        false
+    }
 }
@@ -1,45 +1,73 @@
 use crate::data::{LinkData, PointData};
 use crate::force::utils::{about_zero, jiggle};
-use crate::force::ForceSimulate;
+use crate::force::{ForceContext, ForceSimulate, TickContext};
 use num::Float;
 use std::cmp::min;
+use std::collections::HashMap;
+use std::marker::PhantomData;
 
-pub struct LinkForce<F: Float, const N: usize, D> {
+/// Link strength accessor, called with a link and the degree of every node indexed by [PointData::index]
+pub type LinkStrengthFn<F, L = ()> = Box<dyn Fn(&LinkData<L>, &[usize]) -> F + Send + Sync>;
+/// Link distance accessor, called with a link and all links
+pub type LinkDistanceFn<F, L = ()> = Box<dyn Fn(&LinkData<L>, &[LinkData<L>]) -> F + Send + Sync>;
+
+/// `L` is the payload carried by every link (weight, type...), see [LinkData::data]
+pub struct LinkForce<F: Float, const N: usize, D, L = ()> {
+    /// 修改边请使用set_links/add_link/remove_link，以保持payloads同步
     pub links: Vec<(usize, usize)>,
-    links_data: Vec<LinkData<F, N, D>>,
-    // FIXME &LinkForce<F, N, D>参数是否有更好的初始化办法    ->  使用Box<dyn Fn>？
-    strength_fn: fn(&LinkData<F, N, D>, &LinkForce<F, N, D>) -> F,
+    /// 与links一一对应
+    payloads: Vec<L>,
+    links_data: Vec<LinkData<L>>,
+    strength_fn: LinkStrengthFn<F, L>,
     strengths: Vec<F>,
-    distance_fn: fn(&LinkData<F, N, D>, &[LinkData<F, N, D>]) -> F,
+    distance_fn: LinkDistanceFn<F, L>,
     distances: Vec<F>,
     count: Vec<usize>,
     bias: Vec<F>,
+    /// 节点id -> 与其相连的边的下标
+    node_links: Vec<Vec<usize>>,
+    /// 边的下标 -> 其在links_data中的位置，端点不存在的边为None
+    data_positions: Vec<Option<usize>>,
+    /// 节点id -> 节点在force_point_data中的下标
+    index_map: HashMap<usize, usize>,
     pub iterations: usize,
+    /// 边在init之后被整体替换，需要重新init
+    stale: bool,
+    _point_data: PhantomData<fn(&PointData<F, N, D>)>,
 }
 
-impl<F: Float, const N: usize, D> LinkForce<F, N, D> {
+impl<F: Float + Send + Sync + 'static, const N: usize, D: 'static, L: Clone + 'static>
+    LinkForce<F, N, D, L>
+{
     pub fn new(
-        links: Vec<(usize, usize)>,
-        strength_fn: fn(&LinkData<F, N, D>, &LinkForce<F, N, D>) -> F,
-        distance_fn: fn(&LinkData<F, N, D>, &[LinkData<F, N, D>]) -> F,
+        links: Vec<((usize, usize), L)>,
+        strength_fn: impl Fn(&LinkData<L>, &[usize]) -> F + Send + Sync + 'static,
+        distance_fn: impl Fn(&LinkData<L>, &[LinkData<L>]) -> F + Send + Sync + 'static,
         iterations: usize,
-    ) -> LinkForce<F, N, D> {
+    ) -> LinkForce<F, N, D, L> {
+        let (links, payloads) = links.into_iter().unzip();
         LinkForce {
             links,
-            strength_fn,
-            distance_fn,
+            payloads,
+            strength_fn: Box::new(strength_fn),
+            distance_fn: Box::new(distance_fn),
             iterations,
             links_data: Vec::new(),
             strengths: Vec::new(),
             distances: Vec::new(),
             count: Vec::new(),
             bias: Vec::new(),
+            node_links: Vec::new(),
+            data_positions: Vec::new(),
+            index_map: HashMap::new(),
+            stale: false,
+            _point_data: PhantomData,
         }
     }
 
     fn init_strengths(&mut self) {
         for link in self.links_data.iter() {
-            self.strengths[link.index] = (self.strength_fn)(link, self)
+            self.strengths[link.index] = (self.strength_fn)(link, &self.count)
         }
     }
 
@@ -49,127 +77,324 @@ impl<F: Float, const N: usize, D> LinkForce<F, N, D> {
         }
     }
 
-    pub fn set_links(&mut self, links: Vec<(usize, usize)>) {
-        self.links = links;
+    /// Every link gets the default payload
+    pub fn set_links(&mut self, links: Vec<(usize, usize)>)
+    where
+        L: Default,
+    {
+        self.set_links_with_data(links.into_iter().map(|link| (link, L::default())).collect());
+    }
+
+    pub fn set_links_with_data(&mut self, links: Vec<((usize, usize), L)>) {
+        (self.links, self.payloads) = links.into_iter().unzip();
+        self.links_data.clear();
+        self.count.clear();
+        self.node_links.clear();
+        self.data_positions.clear();
+        self.stale = true;
     }
 
     pub fn set_strength_fn(
         &mut self,
-        strength_fn: fn(&LinkData<F, N, D>, &LinkForce<F, N, D>) -> F,
+        strength_fn: impl Fn(&LinkData<L>, &[usize]) -> F + Send + Sync + 'static,
     ) {
-        self.strength_fn = strength_fn;
+        self.strength_fn = Box::new(strength_fn);
         if self.strengths.len() > 0 {
             self.init_strengths();
         }
     }
 
+    /// Same strength for every link
+    pub fn set_strength(&mut self, strength: F) {
+        self.set_strength_fn(move |_, _| strength);
+    }
+
+    /// Strength of every link, parallel to [LinkForce::links] at the time of the call
+    ///
+    /// The values are kept by the endpoints of the links, so they follow the links moved by
+    /// [LinkForce::remove_link]. Parallel links share the last value, links added later get the
+    /// default strength
+    pub fn set_strengths(&mut self, strengths: Vec<F>) {
+        let strengths = self.by_endpoints(strengths);
+        self.set_strength_fn(
+            move |link, count| match strengths.get(&(link.source, link.target)) {
+                Some(&strength) => strength,
+                None => default_strength_fn(link, count),
+            },
+        );
+    }
+
     pub fn set_distance_fn(
         &mut self,
-        distance_fn: fn(&LinkData<F, N, D>, &[LinkData<F, N, D>]) -> F,
+        distance_fn: impl Fn(&LinkData<L>, &[LinkData<L>]) -> F + Send + Sync + 'static,
     ) {
-        self.distance_fn = distance_fn;
+        self.distance_fn = Box::new(distance_fn);
         if self.distances.len() > 0 {
             self.init_distances();
         }
     }
 
+    /// Same distance for every link
+    pub fn set_distance(&mut self, distance: F) {
+        self.set_distance_fn(move |_, _| distance);
+    }
+
+    /// Distance of every link, parallel to [LinkForce::links] at the time of the call, see
+    /// [LinkForce::set_strengths]
+    pub fn set_distances(&mut self, distances: Vec<F>) {
+        let distances = self.by_endpoints(distances);
+        self.set_distance_fn(
+            move |link, links| match distances.get(&(link.source, link.target)) {
+                Some(&distance) => distance,
+                None => default_distance_fn(link, links),
+            },
+        );
+    }
+
+    /// 以端点id为键保存与links一一对应的值，remove_link改变边的下标后仍能找到
+    fn by_endpoints(&self, values: Vec<F>) -> HashMap<(usize, usize), F> {
+        self.links.iter().copied().zip(values).collect()
+    }
+
     pub fn count(&self) -> &[usize] {
         &self.count
     }
+
+    /// Payload of every link, parallel to [LinkForce::links]
+    pub fn payloads(&self) -> &[L] {
+        &self.payloads
+    }
+
+    /// 初始化后，links与各条边的参数一一对应
+    fn is_initialized(&self) -> bool {
+        self.data_positions.len() == self.links.len()
+    }
+
+    /// Append a link with the default payload, see [LinkForce::add_link_with_data]
+    pub fn add_link(&mut self, link: (usize, usize)) -> usize
+    where
+        L: Default,
+    {
+        self.add_link_with_data(link, L::default())
+    }
+
+    /// Append a link and update degree counts, bias and strengths of the links sharing an endpoint,
+    /// returns the index of the new link
+    ///
+    /// Before [ForceSimulate::init] the link is only recorded and picked up by the next init
+    pub fn add_link_with_data(&mut self, link: (usize, usize), data: L) -> usize {
+        let index = self.links.len();
+        let initialized = self.is_initialized();
+        self.links.push(link);
+        self.payloads.push(data.clone());
+        if !initialized {
+            return index;
+        }
+        self.strengths.push(F::zero());
+        self.distances.push(F::zero());
+        self.bias.push(F::zero());
+        let (s, t) = link;
+        let (source, target) = match (self.index_map.get(&s), self.index_map.get(&t)) {
+            (Some(&source), Some(&target)) => (source, target),
+            _ => {
+                self.data_positions.push(None);
+                return index;
+            }
+        };
+        self.data_positions.push(Some(self.links_data.len()));
+        self.links_data
+            .push(LinkData::new(index, link, (source, target), data));
+        let len = s.max(t) + 1;
+        if self.count.len() < len {
+            self.count.resize(len, 0);
+            self.node_links.resize(len, Vec::new());
+        }
+        self.count[s] += 1;
+        self.count[t] += 1;
+        self.node_links[s].push(index);
+        self.node_links[t].push(index);
+        let link_data = self.links_data.last().unwrap();
+        self.distances[index] = (self.distance_fn)(link_data, self.links_data.as_slice());
+        self.update_links_of(&[s, t]);
+        index
+    }
+
+    /// Remove the first link from `source` to `target`, returns false if there is no such link
+    ///
+    /// The last link takes the index of the removed one, accessors reading per-link values by
+    /// [LinkData::index] have to be set again
+    pub fn remove_link(&mut self, source: usize, target: usize) -> bool {
+        let index = match self.is_initialized() {
+            true => self.node_links.get(source).and_then(|links| {
+                links
+                    .iter()
+                    .copied()
+                    .find(|&i| self.links[i] == (source, target))
+            }),
+            false => None,
+        };
+        // 端点不存在的边不在node_links中
+        let index = index.or_else(|| self.links.iter().position(|&l| l == (source, target)));
+        let index = match index {
+            Some(index) => index,
+            None => return false,
+        };
+        if !self.is_initialized() {
+            self.links.swap_remove(index);
+            self.payloads.swap_remove(index);
+            return true;
+        }
+
+        if let Some(position) = self.data_positions[index] {
+            self.links_data.swap_remove(position);
+            if let Some(moved) = self.links_data.get(position) {
+                self.data_positions[moved.index] = Some(position);
+            }
+            for node in [source, target] {
+                self.count[node] -= 1;
+                self.node_links[node].retain(|&i| i != index);
+            }
+        }
+
+        let last = self.links.len() - 1;
+        self.links.swap_remove(index);
+        self.payloads.swap_remove(index);
+        self.strengths.swap_remove(index);
+        self.distances.swap_remove(index);
+        self.bias.swap_remove(index);
+        self.data_positions.swap_remove(index);
+        if index != last {
+            if let Some(position) = self.data_positions[index] {
+                self.links_data[position].index = index;
+                let (s, t) = self.links[index];
+                for node in [s, t] {
+                    for i in self.node_links[node].iter_mut() {
+                        if *i == last {
+                            *i = index;
+                        }
+                    }
+                }
+            }
+        }
+
+        if source < self.count.len() && target < self.count.len() {
+            self.update_links_of(&[source, target]);
+        }
+        true
+    }
+
+    /// 重新计算与nodes相连的边的bias与strength
+    fn update_links_of(&mut self, nodes: &[usize]) {
+        for &node in nodes {
+            for k in 0..self.node_links[node].len() {
+                let index = self.node_links[node][k];
+                let position = self.data_positions[index].unwrap();
+                let link = &self.links_data[position];
+                let (s, t) = (link.source, link.target);
+                self.bias[index] = F::from(self.count[s]).unwrap()
+                    / F::from(self.count[s] + self.count[t]).unwrap();
+                self.strengths[index] = (self.strength_fn)(link, &self.count);
+            }
+        }
+    }
 }
 
-fn default_strength_fn<F: Float, const N: usize, D>(
-    link: &LinkData<F, N, D>,
-    force: &LinkForce<F, N, D>,
-) -> F {
-    F::one()
-        / F::from(min(
-            force.count()[link.source().index],
-            force.count()[link.target().index],
-        ))
-        .unwrap()
+fn default_strength_fn<F: Float, L>(link: &LinkData<L>, count: &[usize]) -> F {
+    F::one() / F::from(min(count[link.source], count[link.target])).unwrap()
 }
 
-unsafe fn split_borrow_two_diff_index<F: Float, const N: usize, D>(
+fn default_distance_fn<F: Float, L>(_: &LinkData<L>, _: &[LinkData<L>]) -> F {
+    F::from(30_f64).unwrap()
+}
+
+fn split_borrow_two_diff_index<F: Float, const N: usize, D>(
     data: &mut [PointData<F, N, D>],
     source_index: usize,
     target_index: usize,
 ) -> (&mut PointData<F, N, D>, &mut PointData<F, N, D>) {
     assert_ne!(source_index, target_index);
-    let ptr = data.as_mut_ptr();
-    let source = ptr.add(source_index).as_mut().unwrap();
-    let target = ptr.add(target_index).as_mut().unwrap();
-    (source, target)
+    if source_index < target_index {
+        let (left, right) = data.split_at_mut(target_index);
+        (&mut left[source_index], &mut right[0])
+    } else {
+        let (left, right) = data.split_at_mut(source_index);
+        (&mut right[0], &mut left[target_index])
+    }
 }
 
-impl<F: Float, const N: usize, D> Default for LinkForce<F, N, D> {
+impl<F: Float + 'static, const N: usize, D: 'static, L: 'static> Default for LinkForce<F, N, D, L> {
     fn default() -> Self {
         LinkForce {
             links: Vec::new(),
+            payloads: Vec::new(),
             links_data: Vec::new(),
-            strength_fn: default_strength_fn,
-            distance_fn: |_, _| F::from(30_f64).unwrap(),
+            strength_fn: Box::new(default_strength_fn),
+            distance_fn: Box::new(default_distance_fn),
             strengths: Vec::new(),
             distances: Vec::new(),
             count: Vec::new(),
             bias: Vec::new(),
+            node_links: Vec::new(),
+            data_positions: Vec::new(),
+            index_map: HashMap::new(),
             iterations: 1,
+            stale: false,
+            _point_data: PhantomData,
         }
     }
 }
 
-impl<F: Float, const N: usize, D> ForceSimulate<F, N, D> for LinkForce<F, N, D> {
-    fn init(&mut self, force_point_data: &[PointData<F, N, D>]) {
-        self.count = vec![0; force_point_data.len()];
+impl<F: Float + Send + Sync + 'static, const N: usize, D: 'static, L: Clone + 'static>
+    ForceSimulate<F, N, D> for LinkForce<F, N, D, L>
+{
+    fn init(&mut self, context: &ForceContext<F, N, D>) {
+        self.count = vec![0; context.index_len()];
         self.bias = vec![F::zero(); self.links.len()];
 
-        self.links_data = LinkData::from_pairs(&self.links, force_point_data);
+        self.index_map = context.index_map().clone();
+        assert_eq!(
+            self.links.len(),
+            self.payloads.len(),
+            "links和payloads数量不一致"
+        );
+        self.links_data = LinkData::from_pairs(&self.links, &self.payloads, &self.index_map);
 
-        for link in self.links_data.iter() {
-            self.count[link.source().index] = self.count[link.source().index] + 1;
-            self.count[link.target().index] = self.count[link.target().index] + 1;
+        self.node_links = vec![Vec::new(); self.count.len()];
+        self.data_positions = vec![None; self.links.len()];
+        for (position, link) in self.links_data.iter().enumerate() {
+            self.count[link.source] = self.count[link.source] + 1;
+            self.count[link.target] = self.count[link.target] + 1;
+            self.node_links[link.source].push(link.index);
+            self.node_links[link.target].push(link.index);
+            self.data_positions[link.index] = Some(position);
         }
         for link in self.links_data.iter() {
-            self.bias[link.index] = F::from(self.count[link.source().index]).unwrap()
-                / F::from(self.count[link.source().index] + self.count[link.target().index])
-                    .unwrap();
+            self.bias[link.index] = F::from(self.count[link.source]).unwrap()
+                / F::from(self.count[link.source] + self.count[link.target]).unwrap();
         }
-        self.strengths = vec![F::zero(); self.links_data.len()];
-        self.distances = vec![F::zero(); self.links_data.len()];
+        self.strengths = vec![F::zero(); self.links.len()];
+        self.distances = vec![F::zero(); self.links.len()];
         self.init_strengths();
         self.init_distances();
+        self.stale = false;
     }
 
-    fn force(&self, force_point_data: &mut [PointData<F, N, D>], alpha: F) {
-        let mut rnd = rand::thread_rng();
+    fn force(&mut self, context: &mut TickContext<F, N, D>) {
+        let alpha = context.alpha;
         for _ in 0..self.iterations {
             for link in self.links_data.iter() {
-                let source_index = link.source().index;
-                let target_index = link.target().index;
-                // TODO find会不会效率问题？   如果依赖下标不变会好一些？
-                let (mut real_source_index, mut real_target_index) = (0, 0);
-                for (idx, force_point_datum) in force_point_data.iter().enumerate() {
-                    if force_point_datum.index == source_index {
-                        real_source_index = idx
-                    }
-                    if force_point_datum.index == target_index {
-                        real_target_index = idx
-                    }
-                }
-                let (mut source, mut target) = unsafe {
-                    split_borrow_two_diff_index(
-                        force_point_data,
-                        real_source_index,
-                        real_target_index,
-                    )
-                };
+                let (source, target) = split_borrow_two_diff_index(
+                    context.point_data,
+                    link.source_position,
+                    link.target_position,
+                );
 
                 let mut p = [F::zero(); N];
                 for i in 0..N {
                     p[i] =
                         target.coord[i] + target.velocity[i] - source.coord[i] - source.velocity[i];
                     if about_zero(p[i]) {
-                        p[i] = jiggle(&mut rnd)
+                        p[i] = jiggle(context.rng)
                     }
                 }
                 let mut l = p.iter().fold(F::zero(), |s, &x| s + x * x).sqrt();
@@ -188,4 +413,174 @@ impl<F: Float, const N: usize, D> ForceSimulate<F, N, D> for LinkForce<F, N, D>
             }
         }
     }
+
+    fn needs_init(&self) -> bool {
+        self.stale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LinkForce;
+    use crate::data::PointData;
+    use crate::force::{ForceContext, ForceSimulate, TickContext};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn parameters(force: &LinkForce<f64, 2, ()>) -> Vec<((usize, usize), f64, f64, f64)> {
+        let mut parameters = force
+            .links_data
+            .iter()
+            .map(|link| {
+                (
+                    force.links[link.index],
+                    force.strengths[link.index],
+                    force.bias[link.index],
+                    force.distances[link.index],
+                )
+            })
+            .collect::<Vec<_>>();
+        parameters.sort_by(|a, b| a.0.cmp(&b.0));
+        parameters
+    }
+
+    #[test]
+    fn test_incremental_links() {
+        let point_data = (0..6)
+            .map(|i| PointData::from_data((), [i as f64, 0.0], i))
+            .collect::<Vec<_>>();
+        let mut incremental: LinkForce<f64, 2, ()> = LinkForce::default();
+        incremental.set_links(vec![(0, 1), (1, 2)]);
+        incremental.init(&ForceContext::from_point_data(&point_data));
+        assert_eq!(incremental.add_link((2, 3)), 2);
+        incremental.add_link((1, 3));
+        incremental.add_link((3, 9));
+        incremental.add_link((4, 5));
+        assert!(incremental.remove_link(1, 2));
+        assert!(incremental.remove_link(3, 9));
+        assert!(!incremental.remove_link(1, 2));
+
+        let mut full: LinkForce<f64, 2, ()> = LinkForce::default();
+        full.set_links(vec![(0, 1), (2, 3), (1, 3), (4, 5)]);
+        full.init(&ForceContext::from_point_data(&point_data));
+        assert_eq!(incremental.count(), full.count());
+        assert_eq!(parameters(&incremental), parameters(&full));
+    }
+
+    #[test]
+    fn test_link_positions() {
+        // id与在force_point_data中的下标不一致
+        let mut point_data = (0..4)
+            .rev()
+            .map(|i| PointData::from_data((), [i as f64 * 100.0, 0.0], i))
+            .collect::<Vec<_>>();
+        let mut force: LinkForce<f64, 2, ()> = LinkForce::default();
+        force.set_links(vec![(0, 3)]);
+        force.init(&ForceContext::from_point_data(&point_data));
+        let mut rng = StdRng::seed_from_u64(0);
+        force.force(&mut TickContext::new(&mut point_data, 1.0, &mut rng));
+        for point_data in point_data.iter() {
+            match point_data.index {
+                0 => assert!(point_data.velocity[0] > 0.0),
+                3 => assert!(point_data.velocity[0] < 0.0),
+                _ => assert_eq!(point_data.velocity, [0.0, 0.0]),
+            }
+        }
+    }
+
+    #[test]
+    fn test_link_positions_resolved_in_init() {
+        // force按init时解析好的下标直接访问端点，不在force_point_data中查找，每次tick为O(边数)
+        let n = 2_000;
+        let mut point_data = (0..n)
+            .rev()
+            .map(|i| PointData::from_data((), [i as f64, (i % 7) as f64], i))
+            .collect::<Vec<_>>();
+        let mut force: LinkForce<f64, 2, ()> = LinkForce::default();
+        force.set_links((1..n).map(|i| (i - 1, i)).collect());
+        force.init(&ForceContext::from_point_data(&point_data));
+        assert_eq!(force.links_data.len(), n - 1);
+        for link in force.links_data.iter() {
+            assert_eq!(point_data[link.source_position].index, link.source);
+            assert_eq!(point_data[link.target_position].index, link.target);
+        }
+        let mut rng = StdRng::seed_from_u64(0);
+        force.force(&mut TickContext::new(&mut point_data, 1.0, &mut rng));
+        assert!(point_data
+            .iter()
+            .all(|point_data| point_data.velocity != [0.0, 0.0]));
+    }
+
+    #[test]
+    fn test_closure_accessors() {
+        let point_data = (0..4)
+            .map(|i| PointData::from_data((), [i as f64, 0.0], i))
+            .collect::<Vec<_>>();
+        let mut force: LinkForce<f64, 2, ()> = LinkForce::default();
+        force.set_links(vec![(0, 1), (1, 2), (2, 3)]);
+        force.init(&ForceContext::from_point_data(&point_data));
+        // 闭包可以捕获每张图自己的数据
+        let weights = [1.0, 2.0, 4.0];
+        force.set_distance_fn(move |link, _| 10.0 * weights[link.index]);
+        force.set_strengths(vec![0.1, 0.2, 0.3]);
+        assert_eq!(force.distances, vec![10.0, 20.0, 40.0]);
+        assert_eq!(force.strengths, vec![0.1, 0.2, 0.3]);
+
+        force.set_strength(0.5);
+        force.set_distance(5.0);
+        force.add_link((0, 3));
+        assert_eq!(force.distances, vec![5.0; 4]);
+        assert_eq!(force.strengths, vec![0.5; 4]);
+    }
+
+    #[test]
+    fn test_strengths_after_remove_link() {
+        let point_data = (0..4)
+            .map(|i| PointData::from_data((), [i as f64, 0.0], i))
+            .collect::<Vec<_>>();
+        let mut force: LinkForce<f64, 2, ()> = LinkForce::default();
+        force.set_links(vec![(0, 1), (1, 2), (2, 3)]);
+        force.init(&ForceContext::from_point_data(&point_data));
+        force.set_strengths(vec![0.1, 0.2, 0.3]);
+        force.set_distances(vec![10.0, 20.0, 30.0]);
+        // (2, 3)移到下标1，且与被删除的边共享端点2，会被重新计算
+        assert!(force.remove_link(1, 2));
+        assert_eq!(force.links, vec![(0, 1), (2, 3)]);
+        assert_eq!(force.strengths, vec![0.1, 0.3]);
+        assert_eq!(force.distances, vec![10.0, 30.0]);
+
+        force.set_strengths(vec![0.5, 0.6]);
+        force.add_link((1, 2));
+        assert!(force.remove_link(0, 1));
+        assert_eq!(force.links, vec![(1, 2), (2, 3)]);
+        assert_eq!(force.strengths, vec![1.0, 0.6]);
+    }
+
+    #[test]
+    fn test_link_payload() {
+        let point_data = (0..4)
+            .map(|i| PointData::from_data((), [i as f64, 0.0], i))
+            .collect::<Vec<_>>();
+        let mut force: LinkForce<f64, 2, (), (f64, &str)> = LinkForce::default();
+        force.set_links_with_data(vec![
+            ((0, 1), (1.0, "weak")),
+            ((1, 2), (2.0, "strong")),
+            ((2, 3), (3.0, "weak")),
+        ]);
+        force.set_distance_fn(|link, _| 10.0 * link.data().0);
+        force.set_strength_fn(|link, _| match link.data().1 {
+            "strong" => 1.0,
+            _ => 0.1,
+        });
+        force.init(&ForceContext::from_point_data(&point_data));
+        assert_eq!(force.distances, vec![10.0, 20.0, 30.0]);
+        assert_eq!(force.strengths, vec![0.1, 1.0, 0.1]);
+
+        force.add_link_with_data((0, 3), (4.0, "strong"));
+        assert!(force.remove_link(0, 1));
+        assert_eq!(force.links, vec![(0, 3), (1, 2), (2, 3)]);
+        assert_eq!(force.payloads()[0], (4.0, "strong"));
+        assert_eq!(force.distances, vec![40.0, 20.0, 30.0]);
+        assert_eq!(force.strengths, vec![1.0, 1.0, 0.1]);
+    }
 }
@@ -1,12 +1,18 @@
+mod boundary_force;
 mod center_force;
+mod cluster_force;
 mod force;
 mod link_force;
 mod nbody_force;
 mod position_force;
-mod utils;
+mod radial_force;
+pub(crate) mod utils;
 
+pub use boundary_force::{Boundary, BoundaryForce, BoundaryMode};
 pub use center_force::CenterForce;
-pub use force::ForceSimulate;
-pub use link_force::LinkForce;
+pub use cluster_force::ClusterForce;
+pub use force::{ForceContext, ForceSimulate, NodeFn, TickContext};
+pub use link_force::{LinkDistanceFn, LinkForce, LinkStrengthFn};
 pub use nbody_force::NBodyForce;
 pub use position_force::PositionForce;
+pub use radial_force::RadialForce;
@@ -1,29 +1,52 @@
 use crate::data::{ForceData, PointData, PointForceData};
 use crate::force::utils::{about_zero, jiggle};
-use crate::force::ForceSimulate;
+use crate::force::{ForceContext, ForceSimulate, NodeFn, TickContext};
 use bumpalo_herd::Herd;
 use generic_tree::{GenericTree, Node, TreeData};
 use num::Float;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::collections::HashSet;
 use std::fmt::{Debug, Display, Formatter};
 
 pub struct NBodyForce<F: Float, const N: usize, const N2: usize, D> {
     pub distance_min: F,
     pub distance_max: F,
     pub theta: F,
-    pub strength_fn: fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F,
+    pub strength_fn: NodeFn<F, N, D, F>,
+    /// Evaluate the tree for every point on the rayon pool
+    pub parallel: bool,
     strengths: Vec<F>,
-    force_point_data: Option<*const [PointData<F, N, D>]>,
+    /// strength_fn在init之后被修改，需要重新init
+    stale: bool,
+    /// 构建树所用的内存，每次tick复用
+    herd: Herd,
+    /// 每个点本次受到的速度变化
+    velocities: Vec<[F; N]>,
 }
 
-impl<F: Float, const N: usize, const N2: usize, D> Default for NBodyForce<F, N, N2, D> {
+fn default_strength_fn<F: Float, const N: usize, D>(
+    _: &PointData<F, N, D>,
+    _: &[PointData<F, N, D>],
+) -> F {
+    F::from(-30_f64).unwrap()
+}
+
+impl<F: Float + 'static, const N: usize, const N2: usize, D: 'static> Default
+    for NBodyForce<F, N, N2, D>
+{
     fn default() -> Self {
         NBodyForce {
             distance_min: F::from(0_f64).unwrap(),
             distance_max: F::infinity(),
             theta: F::from(0.9_f64).unwrap(),
-            strength_fn: |_, _| F::from(-30_f64).unwrap(),
+            strength_fn: Box::new(default_strength_fn),
+            parallel: true,
             strengths: Vec::new(),
-            force_point_data: None,
+            stale: false,
+            herd: Herd::new(),
+            velocities: Vec::new(),
         }
     }
 }
@@ -34,41 +57,59 @@ impl<F: Float, const N: usize, const N2: usize, D> Debug for NBodyForce<F, N, N2
             .field("distance_min", &self.distance_min.to_f64())
             .field("distance_max", &self.distance_max.to_f64())
             .field("theta", &self.theta.to_f64())
+            .field("parallel", &self.parallel)
             .finish()
     }
 }
 
 impl<
-        F: Float + Send + Sync,
+        F: Float + Send + Sync + 'static,
         const N: usize,
         const N2: usize,
-        D: Default + Display + Clone + Send + Sync,
+        D: Default + Display + Clone + Send + Sync + 'static,
     > NBodyForce<F, N, N2, D>
 {
     pub fn new(
         distance_min: F,
         distance_max: F,
         theta: F,
-        strength_fn: fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F,
+        strength_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F + Send + Sync + 'static,
     ) -> NBodyForce<F, N, N2, D> {
         NBodyForce {
             distance_min,
             distance_max,
             theta,
-            strength_fn,
+            strength_fn: Box::new(strength_fn),
+            parallel: true,
             strengths: Vec::new(),
-            force_point_data: None,
+            stale: false,
+            herd: Herd::new(),
+            velocities: Vec::new(),
         }
     }
 
     pub fn set_strength_fn(
         &mut self,
-        strength_fn: fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F,
+        strength_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F + Send + Sync + 'static,
     ) {
-        self.strength_fn = strength_fn;
-        if let Some(force_point_data) = self.force_point_data {
-            unsafe { self.init(force_point_data.as_ref().unwrap()) }
-        }
+        self.strength_fn = Box::new(strength_fn);
+        self.stale = true;
+    }
+
+    /// Same strength for every node
+    pub fn set_strength(&mut self, strength: F) {
+        self.set_strength_fn(move |_, _| strength);
+    }
+
+    /// Strength of every node, indexed by [PointData::index], nodes past the end (added later)
+    /// get the default strength
+    pub fn set_strengths(&mut self, strengths: Vec<F>) {
+        self.set_strength_fn(move |point_data, nodes| {
+            strengths
+                .get(point_data.index)
+                .copied()
+                .unwrap_or_else(|| default_strength_fn(point_data, nodes))
+        });
     }
 
     fn accumulate(&self, node: &mut Node<F, N, N2, ForceData<F, N, D>>) {
@@ -109,17 +150,121 @@ impl<
         }
     }
 
-    fn apply(
+    fn barnes_hut(&self, alpha: F) -> BarnesHut<F> {
+        BarnesHut {
+            distance_min: self.distance_min,
+            distance_max: self.distance_max,
+            theta: self.theta,
+            alpha,
+        }
+    }
+}
+
+impl<
+        F: Float + Send + Sync + 'static,
+        const N: usize,
+        const N2: usize,
+        D: Default + Display + Clone + Send + Sync + 'static,
+    > ForceSimulate<F, N, D> for NBodyForce<F, N, N2, D>
+{
+    fn init(&mut self, context: &ForceContext<F, N, D>) {
+        let force_point_data = context.point_data();
+        self.strengths = vec![F::zero(); context.index_len()];
+        for point_data in force_point_data {
+            self.strengths[point_data.index] = (self.strength_fn)(point_data, force_point_data)
+        }
+        self.stale = false;
+    }
+
+    fn force(&mut self, context: &mut TickContext<F, N, D>) {
+        let alpha = context.alpha;
+        let force_point_data = &mut *context.point_data;
+        // for point_data in force_point_data.iter() {
+        //     println!("更新前数据 {}", point_data)
+        // }
+        // 上一次的树已经释放，复用其内存
+        self.herd.reset();
+        let mut velocities = std::mem::take(&mut self.velocities);
+        // 坐标相同的节点（如被边界夹到同一个角上）无法被树分开，建树时稍微错开
+        let mut seen = HashSet::with_capacity(force_point_data.len());
+        let rng = &mut *context.rng;
+        let mut tree = GenericTree::<F, N, N2, ForceData<F, N, D>>::from_nodes(
+            &self.herd,
+            force_point_data
+                .iter_mut()
+                .map(|point_data| {
+                    let mut coord = point_data.coord;
+                    while !seen.insert(coord.map(|x| x.integer_decode())) {
+                        coord[0] = coord[0] + jiggle::<F, _>(rng) * (F::one() + coord[0].abs());
+                    }
+                    Node::new_point(coord, PointForceData::from_point_data(point_data))
+                })
+                .collect::<Vec<_>>(),
+            // TODO 参数设置
+            F::infinity(),
+            // 叶子区域的节点数必须小于2^N
+            (N2 - 1) as u32,
+        );
+        tree.visit_post_order_mut(|node, _| self.accumulate(node));
+        // 树在此之后只读，每个点的受力可以独立计算
+        let tree = &tree;
+        let barnes_hut = self.barnes_hut(alpha);
+        // 每个点使用由本次seed派生的独立随机数，使并行与串行结果一致
+        let seed: u64 = context.rng.gen();
+        let evaluate = |point_data: &PointData<F, N, D>| {
+            let mut rnd = StdRng::seed_from_u64(seed ^ point_data.index as u64);
+            let mut velocity = [F::zero(); N];
+            tree.visit_pre_order(|node, _| {
+                barnes_hut.apply(point_data, &mut velocity, node, &mut rnd)
+            });
+            velocity
+        };
+        if self.parallel {
+            force_point_data
+                .par_iter()
+                .map(evaluate)
+                .collect_into_vec(&mut velocities);
+        } else {
+            velocities.clear();
+            velocities.extend(force_point_data.iter().map(evaluate));
+        }
+        for (point_data, velocity) in force_point_data.iter_mut().zip(velocities.iter()) {
+            for i in 0..N {
+                point_data.velocity[i] = point_data.velocity[i] + velocity[i];
+            }
+        }
+        self.velocities = velocities;
+    }
+
+    fn needs_init(&self) -> bool {
+        self.stale
+    }
+}
+
+/// Read-only parameters used while walking the tree, shared by every worker
+#[derive(Clone, Copy)]
+struct BarnesHut<F: Float> {
+    distance_min: F,
+    distance_max: F,
+    theta: F,
+    alpha: F,
+}
+
+impl<F: Float + Send + Sync> BarnesHut<F> {
+    /// Accumulate the force of `node` on `point_data` into `velocity`
+    ///
+    /// Returns true if the children of `node` need not be visited
+    fn apply<const N: usize, const N2: usize, D: Default + Display + Clone + Send + Sync>(
         &self,
-        point_data: &mut PointData<F, N, D>,
+        point_data: &PointData<F, N, D>,
+        velocity: &mut [F; N],
         node: &Node<F, N, N2, ForceData<F, N, D>>,
-        alpha: F,
+        rnd: &mut StdRng,
     ) -> bool {
         if node.is_region() && !node.has_children() {
             // 跳过无children的Region
             return true;
         }
-        let mut rnd = rand::thread_rng();
         // FIXME node的strength 是否会存在未被初始化
         let (_strength, _coord) = match node {
             Node::Point { data, .. } => (Some(data.strength), Some(data.coord)),
@@ -140,7 +285,7 @@ impl<
                 if l < self.distance_max.powi(2) {
                     for i in 0..N {
                         if about_zero(_coord[i] - point_data.coord[i]) {
-                            let _x: F = jiggle::<F>(&mut rnd);
+                            let _x: F = jiggle(rnd);
                             l = l + _x.powi(2)
                         }
                         if l < self.distance_min.powi(2) {
@@ -148,8 +293,9 @@ impl<
                             l = _t.sqrt()
                         }
                         for j in 0..N {
-                            let _d: F = (_coord[j] - point_data.coord[j]) * _strength * alpha / l;
-                            point_data.velocity[j] = point_data.velocity[j] + _d;
+                            let _d: F =
+                                (_coord[j] - point_data.coord[j]) * _strength * self.alpha / l;
+                            velocity[j] = velocity[j] + _d;
                         }
                     }
                 }
@@ -161,7 +307,7 @@ impl<
             if point_data.index != node.data().index {
                 for i in 0..N {
                     if about_zero(_coord[i] - point_data.coord[i]) {
-                        let _x: F = jiggle::<F>(&mut rnd);
+                        let _x: F = jiggle(rnd);
                         l = l + _x.powi(2)
                     }
                     if l < self.distance_min.powi(2) {
@@ -169,10 +315,10 @@ impl<
                         l = _t.sqrt()
                     }
                 }
-                let w = self.strengths[node.data().index] * alpha / l;
+                let w = _strength * self.alpha / l;
                 for j in 0..N {
                     let _d: F = (_coord[j] - point_data.coord[j]) * w;
-                    point_data.velocity[j] = point_data.velocity[j] + _d;
+                    velocity[j] = velocity[j] + _d;
                 }
             }
             false
@@ -181,59 +327,4 @@ impl<
             true
         }
     }
-
-    fn _set_strength(&mut self) {
-        if let Some(force_point_data) = self.force_point_data {
-            unsafe {
-                (&*force_point_data).iter().for_each(|point_data| {
-                    self.strengths[point_data.index] =
-                        (self.strength_fn)(point_data, &*force_point_data)
-                })
-            }
-        }
-    }
-}
-
-impl<
-        F: Float + Send + Sync,
-        const N: usize,
-        const N2: usize,
-        D: Default + Display + Clone + Send + Sync,
-    > ForceSimulate<F, N, D> for NBodyForce<F, N, N2, D>
-{
-    fn init(&mut self, force_point_data: &[PointData<F, N, D>]) {
-        self.force_point_data = Some(force_point_data as *const [PointData<F, N, D>]);
-        self.strengths = vec![F::zero(); force_point_data.len()];
-        self._set_strength()
-    }
-
-    fn force(&self, force_point_data: &mut [PointData<F, N, D>], alpha: F) {
-        // for point_data in force_point_data.iter() {
-        //     println!("更新前数据 {}", point_data)
-        // }
-        // TODO 效率
-        let herd = Herd::new();
-        let mut tree = GenericTree::<F, N, N2, ForceData<F, N, D>>::from_nodes(
-            &herd,
-            force_point_data
-                .iter_mut()
-                .map(|point_data| {
-                    Node::new_point(
-                        point_data.coord,
-                        PointForceData::from_point_data(point_data),
-                    )
-                })
-                .collect::<Vec<_>>(),
-            // TODO 参数设置
-            F::infinity(),
-            (N.pow(2_u32) - 1) as u32,
-        );
-        tree.visit_post_order_mut(|node, _| self.accumulate(node));
-        for point_data in force_point_data.iter_mut() {
-            tree.visit_pre_order_mut(|node, _| self.apply(point_data, node, alpha));
-        }
-        // for point_data in force_point_data.iter() {
-        //     println!("更新后数据 {}", point_data)
-        // }
-    }
 }
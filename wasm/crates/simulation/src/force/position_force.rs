@@ -1,95 +1,140 @@
 use crate::data::PointData;
-use crate::force::ForceSimulate;
+use crate::force::{ForceContext, ForceSimulate, NodeFn, TickContext};
 use num::Float;
 use std::fmt::Display;
 
 pub struct PositionForce<F: Float, const N: usize, D> {
     /// 目标点，如果对应维度是None则表示不进行对应维度力的模拟
-    pub target_position_fn: fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> [Option<F>; N],
-    pub strength_fn: fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> [Option<F>; N],
+    pub target_position_fn: NodeFn<F, N, D, [Option<F>; N]>,
+    pub strength_fn: NodeFn<F, N, D, [Option<F>; N]>,
     target_position: Vec<[Option<F>; N]>,
     strengths: Vec<[Option<F>; N]>,
-    force_point_data: Option<*const [PointData<F, N, D>]>,
+    /// 访问器在init之后被修改，需要重新init
+    stale: bool,
 }
 
-impl<F: Float, const N: usize, D> PositionForce<F, N, D> {
+impl<F: Float + Send + Sync + 'static, const N: usize, D: 'static> PositionForce<F, N, D> {
     pub fn new(
-        target_position_fn: fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> [Option<F>; N],
-        strength_fn: fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> [Option<F>; N],
+        target_position_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> [Option<F>; N]
+            + Send
+            + Sync
+            + 'static,
+        strength_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> [Option<F>; N]
+            + Send
+            + Sync
+            + 'static,
     ) -> PositionForce<F, N, D> {
         PositionForce {
-            target_position_fn,
-            strength_fn,
+            target_position_fn: Box::new(target_position_fn),
+            strength_fn: Box::new(strength_fn),
             target_position: Vec::new(),
             strengths: Vec::new(),
-            force_point_data: None,
-        }
-    }
-
-    fn _set_target_position(&mut self) {
-        if let Some(force_point_data) = self.force_point_data {
-            unsafe {
-                for point_data in &*force_point_data {
-                    self.target_position[point_data.index] =
-                        (self.target_position_fn)(point_data, &*force_point_data)
-                }
-            }
+            stale: false,
         }
     }
 
     pub fn set_target_position_fn(
         &mut self,
-        target_position_fn: fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> [Option<F>; N],
+        target_position_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> [Option<F>; N]
+            + Send
+            + Sync
+            + 'static,
     ) {
-        self.target_position_fn = target_position_fn;
-        self._set_target_position();
+        self.target_position_fn = Box::new(target_position_fn);
+        self.stale = true;
+    }
+
+    /// Same target position for every node
+    pub fn set_target_position(&mut self, target_position: [Option<F>; N]) {
+        self.set_target_position_fn(move |_, _| target_position);
+    }
+
+    /// Target position of every node, indexed by [PointData::index], nodes past the end (added
+    /// later) get the default target
+    pub fn set_target_positions(&mut self, target_positions: Vec<[Option<F>; N]>) {
+        self.set_target_position_fn(move |point_data, nodes| {
+            target_positions
+                .get(point_data.index)
+                .copied()
+                .unwrap_or_else(|| default_target_position_fn(point_data, nodes))
+        });
     }
 
     pub fn set_strength_fn(
         &mut self,
-        strength_fn: fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> [Option<F>; N],
+        strength_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> [Option<F>; N]
+            + Send
+            + Sync
+            + 'static,
     ) {
-        self.strength_fn = strength_fn;
-        self._set_strength();
+        self.strength_fn = Box::new(strength_fn);
+        self.stale = true;
     }
 
-    fn _set_strength(&mut self) {
-        if let Some(force_point_data) = self.force_point_data {
-            unsafe {
-                for point_data in &*force_point_data {
-                    self.strengths[point_data.index] =
-                        (self.strength_fn)(point_data, &*force_point_data)
-                }
-            }
-        }
+    /// Same strength for every node
+    pub fn set_strength(&mut self, strength: [Option<F>; N]) {
+        self.set_strength_fn(move |_, _| strength);
+    }
+
+    /// Strength of every node, indexed by [PointData::index], nodes past the end get the default
+    /// strength
+    pub fn set_strengths(&mut self, strengths: Vec<[Option<F>; N]>) {
+        self.set_strength_fn(move |point_data, nodes| {
+            strengths
+                .get(point_data.index)
+                .copied()
+                .unwrap_or_else(|| default_strength_fn(point_data, nodes))
+        });
     }
 }
 
-impl<F: Float, const N: usize, D> Default for PositionForce<F, N, D> {
+fn default_target_position_fn<F: Float, const N: usize, D>(
+    _: &PointData<F, N, D>,
+    _: &[PointData<F, N, D>],
+) -> [Option<F>; N] {
+    [Some(F::zero()); N]
+}
+
+fn default_strength_fn<F: Float, const N: usize, D>(
+    _: &PointData<F, N, D>,
+    _: &[PointData<F, N, D>],
+) -> [Option<F>; N] {
+    [Some(F::from(0.1f64).unwrap()); N]
+}
+
+impl<F: Float + 'static, const N: usize, D: 'static> Default for PositionForce<F, N, D> {
     fn default() -> Self {
         PositionForce {
-            target_position_fn: |_, _| [Some(F::zero()); N],
-            strength_fn: |_, _| [Some(F::from(0.1f64).unwrap()); N],
+            target_position_fn: Box::new(default_target_position_fn),
+            strength_fn: Box::new(default_strength_fn),
             target_position: Vec::new(),
             strengths: Vec::new(),
-            force_point_data: None,
+            stale: false,
         }
     }
 }
 
-impl<F: Float + Send + Sync, const N: usize, D: Default + Display + Clone + Send + Sync>
-    ForceSimulate<F, N, D> for PositionForce<F, N, D>
+impl<
+        F: Float + Send + Sync + 'static,
+        const N: usize,
+        D: Default + Display + Clone + Send + Sync + 'static,
+    > ForceSimulate<F, N, D> for PositionForce<F, N, D>
 {
-    fn init(&mut self, force_point_data: &[PointData<F, N, D>]) {
-        self.force_point_data = Some(force_point_data as *const [PointData<F, N, D>]);
-        self.target_position = vec![[None; N]; force_point_data.len()];
-        self.strengths = vec![[None; N]; force_point_data.len()];
-        self._set_target_position();
-        self._set_strength();
+    fn init(&mut self, context: &ForceContext<F, N, D>) {
+        let force_point_data = context.point_data();
+        self.target_position = vec![[None; N]; context.index_len()];
+        self.strengths = vec![[None; N]; context.index_len()];
+        for point_data in force_point_data {
+            self.target_position[point_data.index] =
+                (self.target_position_fn)(point_data, force_point_data);
+            self.strengths[point_data.index] = (self.strength_fn)(point_data, force_point_data);
+        }
+        self.stale = false;
     }
 
-    fn force(&self, force_point_data: &mut [PointData<F, N, D>], alpha: F) {
-        force_point_data.iter_mut().for_each(|point_data| {
+    fn force(&mut self, context: &mut TickContext<F, N, D>) {
+        let alpha = context.alpha;
+        context.point_data.iter_mut().for_each(|point_data| {
             for i in 0..N {
                 if let (Some(target_position_i), Some(strengths_i)) = (
                     self.target_position[point_data.index][i],
@@ -101,4 +146,8 @@ impl<F: Float + Send + Sync, const N: usize, D: Default + Display + Clone + Send
             }
         })
     }
+
+    fn needs_init(&self) -> bool {
+        self.stale
+    }
 }
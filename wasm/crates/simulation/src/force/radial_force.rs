@@ -0,0 +1,184 @@
+use crate::data::PointData;
+use crate::force::utils::about_zero;
+use crate::force::{ForceContext, ForceSimulate, NodeFn, TickContext};
+use num::Float;
+
+/// Pushes every node toward a circle (a sphere in 3-D) of a per-node radius around `center`
+pub struct RadialForce<F: Float, const N: usize, D> {
+    pub center: [F; N],
+    pub radius_fn: NodeFn<F, N, D, F>,
+    pub strength_fn: NodeFn<F, N, D, F>,
+    radiuses: Vec<F>,
+    strengths: Vec<F>,
+    /// 访问器在init之后被修改，需要重新init
+    stale: bool,
+}
+
+impl<F: Float + Send + Sync + 'static, const N: usize, D: 'static> RadialForce<F, N, D> {
+    pub fn new(
+        center: [F; N],
+        radius_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F + Send + Sync + 'static,
+        strength_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F + Send + Sync + 'static,
+    ) -> RadialForce<F, N, D> {
+        RadialForce {
+            center,
+            radius_fn: Box::new(radius_fn),
+            strength_fn: Box::new(strength_fn),
+            radiuses: Vec::new(),
+            strengths: Vec::new(),
+            stale: false,
+        }
+    }
+
+    pub fn set_center(&mut self, center: [F; N]) {
+        self.center = center;
+    }
+
+    pub fn set_radius_fn(
+        &mut self,
+        radius_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F + Send + Sync + 'static,
+    ) {
+        self.radius_fn = Box::new(radius_fn);
+        self.stale = true;
+    }
+
+    /// Same radius for every node
+    pub fn set_radius(&mut self, radius: F) {
+        self.set_radius_fn(move |_, _| radius);
+    }
+
+    /// Radius of every node, indexed by [PointData::index], nodes past the end (added later) get
+    /// the default radius
+    pub fn set_radiuses(&mut self, radiuses: Vec<F>) {
+        self.set_radius_fn(move |point_data, nodes| {
+            radiuses
+                .get(point_data.index)
+                .copied()
+                .unwrap_or_else(|| default_radius_fn(point_data, nodes))
+        });
+    }
+
+    pub fn set_strength_fn(
+        &mut self,
+        strength_fn: impl Fn(&PointData<F, N, D>, &[PointData<F, N, D>]) -> F + Send + Sync + 'static,
+    ) {
+        self.strength_fn = Box::new(strength_fn);
+        self.stale = true;
+    }
+
+    /// Same strength for every node
+    pub fn set_strength(&mut self, strength: F) {
+        self.set_strength_fn(move |_, _| strength);
+    }
+
+    /// Strength of every node, indexed by [PointData::index], nodes past the end get the default
+    /// strength
+    pub fn set_strengths(&mut self, strengths: Vec<F>) {
+        self.set_strength_fn(move |point_data, nodes| {
+            strengths
+                .get(point_data.index)
+                .copied()
+                .unwrap_or_else(|| default_strength_fn(point_data, nodes))
+        });
+    }
+}
+
+fn default_radius_fn<F: Float, const N: usize, D>(
+    _: &PointData<F, N, D>,
+    _: &[PointData<F, N, D>],
+) -> F {
+    F::from(100_f64).unwrap()
+}
+
+fn default_strength_fn<F: Float, const N: usize, D>(
+    _: &PointData<F, N, D>,
+    _: &[PointData<F, N, D>],
+) -> F {
+    F::from(0.1_f64).unwrap()
+}
+
+impl<F: Float + 'static, const N: usize, D: 'static> Default for RadialForce<F, N, D> {
+    fn default() -> Self {
+        RadialForce {
+            center: [F::zero(); N],
+            radius_fn: Box::new(default_radius_fn),
+            strength_fn: Box::new(default_strength_fn),
+            radiuses: Vec::new(),
+            strengths: Vec::new(),
+            stale: false,
+        }
+    }
+}
+
+impl<F: Float + Send + Sync + 'static, const N: usize, D: 'static> ForceSimulate<F, N, D>
+    for RadialForce<F, N, D>
+{
+    fn init(&mut self, context: &ForceContext<F, N, D>) {
+        let force_point_data = context.point_data();
+        self.radiuses = vec![F::zero(); context.index_len()];
+        self.strengths = vec![F::zero(); context.index_len()];
+        for point_data in force_point_data {
+            self.radiuses[point_data.index] = (self.radius_fn)(point_data, force_point_data);
+            self.strengths[point_data.index] = (self.strength_fn)(point_data, force_point_data);
+        }
+        self.stale = false;
+    }
+
+    fn force(&mut self, context: &mut TickContext<F, N, D>) {
+        let alpha = context.alpha;
+        let tiny = F::from(1e-6_f64).unwrap();
+        for point_data in context.point_data.iter_mut() {
+            let mut d = [F::zero(); N];
+            for i in 0..N {
+                d[i] = point_data.coord[i] - self.center[i];
+                if about_zero(d[i]) {
+                    d[i] = tiny
+                }
+            }
+            let r = d.iter().fold(F::zero(), |s, &x| s + x * x).sqrt();
+            let k = (self.radiuses[point_data.index] - r) * self.strengths[point_data.index]
+                * alpha
+                / r;
+            for i in 0..N {
+                point_data.velocity[i] = point_data.velocity[i] + d[i] * k;
+            }
+        }
+    }
+
+    fn needs_init(&self) -> bool {
+        self.stale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RadialForce;
+    use crate::Simulation;
+
+    #[test]
+    fn test_radial_force() {
+        fn settle<const N: usize>() {
+            let mut simulation: Simulation<f64, N, i32> =
+                Simulation::from_data(Vec::from_iter(0..20));
+            simulation.add_force(
+                String::from("radial"),
+                Box::new(RadialForce::new([1.0; N], |_, _| 50.0, |_, _| 0.5)),
+            );
+            for _ in 0..300 {
+                simulation.tick();
+            }
+            for point_data in simulation.force_point_data.iter() {
+                let r = point_data
+                    .coord
+                    .iter()
+                    .fold(0.0, |s, &x| s + (x - 1.0) * (x - 1.0))
+                    .sqrt();
+                assert!((r - 50.0).abs() < 1.0, "N = {}, r = {}", N, r);
+            }
+        }
+        settle::<1>();
+        settle::<2>();
+        settle::<3>();
+        settle::<4>();
+    }
+}
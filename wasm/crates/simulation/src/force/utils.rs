@@ -1,11 +1,10 @@
 use crate::data::{ForceData, PointData};
 use generic_tree::Node;
 use num::Float;
-use rand::prelude::ThreadRng;
 use rand::Rng;
 use std::fmt::Display;
 
-pub fn jiggle<F: Float>(rng: &mut ThreadRng) -> F {
+pub fn jiggle<F: Float, R: Rng + ?Sized>(rng: &mut R) -> F {
     let x = rng.gen_range(0.0..=1.0);
     F::from((x - 0.5) * 1e-6).unwrap()
 }
@@ -14,6 +13,15 @@ pub fn about_zero<F: Float>(x: F) -> bool {
     x.abs() <= F::epsilon()
 }
 
+/// Length of a buffer indexed by [PointData::index], ids may have gaps after nodes are removed
+pub fn index_len<F: Float, const N: usize, D>(force_point_data: &[PointData<F, N, D>]) -> usize {
+    force_point_data
+        .iter()
+        .map(|point_data| point_data.index + 1)
+        .max()
+        .unwrap_or(0)
+}
+
 pub fn print_node_data<
     F: Float + Send + Sync,
     const N: usize,
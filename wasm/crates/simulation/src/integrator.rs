@@ -0,0 +1,196 @@
+use crate::data::PointData;
+use crate::force::utils::index_len;
+use num::Float;
+
+/// Turns the velocity changes written by the forces into new positions
+///
+/// Forces add their impulse of the tick to [PointData::velocity], an integrator decides how that
+/// impulse and the velocity of the previous tick move the node. Fixed nodes are reset by the
+/// [crate::Simulation] afterwards
+pub trait Integrator<F: Float, const N: usize, D> {
+    /// Called right before the forces of a tick are applied
+    fn before_forces(&mut self, _point_data: &[PointData<F, N, D>]) {}
+
+    /// Update velocity and coordinates of every node
+    fn integrate(&mut self, point_data: &mut [PointData<F, N, D>], velocity_decay: F);
+
+    /// Forget any state collected so far, called by [crate::Simulation::restart]
+    fn reset(&mut self) {}
+}
+
+/// `v *= velocity_decay; x += v` (d3 default)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EulerIntegrator;
+
+impl<F: Float, const N: usize, D> Integrator<F, N, D> for EulerIntegrator {
+    fn integrate(&mut self, point_data: &mut [PointData<F, N, D>], velocity_decay: F) {
+        for point_data in point_data.iter_mut() {
+            for i in 0..N {
+                point_data.velocity[i] = point_data.velocity[i] * velocity_decay;
+                point_data.coord[i] = point_data.coord[i] + point_data.velocity[i];
+            }
+        }
+    }
+}
+
+/// Keep the velocity of every node before the forces ran, indexed by [PointData::index]
+fn snapshot_velocities<F: Float, const N: usize, D>(
+    velocities: &mut Vec<[F; N]>,
+    point_data: &[PointData<F, N, D>],
+) {
+    velocities.resize(index_len(point_data), [F::zero(); N]);
+    for point_data in point_data.iter() {
+        velocities[point_data.index] = point_data.velocity;
+    }
+}
+
+/// Velocity Verlet: the impulse of a tick is split between the position update of this tick and
+/// the velocity update of the next one, which removes most of Euler's overshoot
+#[derive(Clone, Debug)]
+pub struct VerletIntegrator<F: Float, const N: usize> {
+    /// 施加力之前的速度
+    velocities: Vec<[F; N]>,
+    /// 上一次tick的加速度
+    accelerations: Vec<[F; N]>,
+}
+
+impl<F: Float, const N: usize> VerletIntegrator<F, N> {
+    pub fn new() -> VerletIntegrator<F, N> {
+        VerletIntegrator {
+            velocities: Vec::new(),
+            accelerations: Vec::new(),
+        }
+    }
+}
+
+impl<F: Float, const N: usize> Default for VerletIntegrator<F, N> {
+    fn default() -> Self {
+        VerletIntegrator::new()
+    }
+}
+
+impl<F: Float, const N: usize, D> Integrator<F, N, D> for VerletIntegrator<F, N> {
+    fn before_forces(&mut self, point_data: &[PointData<F, N, D>]) {
+        snapshot_velocities(&mut self.velocities, point_data);
+    }
+
+    fn integrate(&mut self, point_data: &mut [PointData<F, N, D>], velocity_decay: F) {
+        let half = F::from(0.5).unwrap();
+        self.accelerations
+            .resize(index_len(point_data), [F::zero(); N]);
+        for point_data in point_data.iter_mut() {
+            let velocity = self.velocities[point_data.index];
+            let last_acceleration = self.accelerations[point_data.index];
+            for i in 0..N {
+                let acceleration = point_data.velocity[i] - velocity[i];
+                let v =
+                    (velocity[i] + (last_acceleration[i] + acceleration) * half) * velocity_decay;
+                point_data.velocity[i] = v;
+                point_data.coord[i] = point_data.coord[i] + v + acceleration * half;
+                self.accelerations[point_data.index][i] = acceleration;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.velocities.clear();
+        self.accelerations.clear();
+    }
+}
+
+/// Adaptive step length after ForceAtlas2's speed control
+///
+/// The swing of a node is how much its impulse changed direction since the last tick and its
+/// traction how consistently it points the same way. The global speed follows
+/// `tolerance * traction / swing` and every node moves at `speed / (1 + sqrt(speed * swing))`,
+/// so oscillating nodes slow down while the rest of the graph keeps converging
+#[derive(Clone, Debug)]
+pub struct AdaptiveIntegrator<F: Float, const N: usize> {
+    /// 越大越容忍抖动
+    pub tolerance: F,
+    /// 每次tick全局速度最多增长的比例
+    pub max_rise: F,
+    /// 全局速度上限，默认为1，即只会减缓振荡的节点
+    pub max_speed: F,
+    speed: F,
+    velocities: Vec<[F; N]>,
+    accelerations: Vec<[F; N]>,
+}
+
+impl<F: Float, const N: usize> AdaptiveIntegrator<F, N> {
+    pub fn new(tolerance: F, max_rise: F, max_speed: F) -> AdaptiveIntegrator<F, N> {
+        AdaptiveIntegrator {
+            tolerance,
+            max_rise,
+            max_speed,
+            speed: F::one(),
+            velocities: Vec::new(),
+            accelerations: Vec::new(),
+        }
+    }
+
+    /// Current global speed
+    pub fn speed(&self) -> F {
+        self.speed
+    }
+}
+
+impl<F: Float, const N: usize> Default for AdaptiveIntegrator<F, N> {
+    fn default() -> Self {
+        AdaptiveIntegrator::new(F::one(), F::from(0.5).unwrap(), F::one())
+    }
+}
+
+impl<F: Float, const N: usize, D> Integrator<F, N, D> for AdaptiveIntegrator<F, N> {
+    fn before_forces(&mut self, point_data: &[PointData<F, N, D>]) {
+        snapshot_velocities(&mut self.velocities, point_data);
+    }
+
+    fn integrate(&mut self, point_data: &mut [PointData<F, N, D>], velocity_decay: F) {
+        let half = F::from(0.5).unwrap();
+        self.accelerations
+            .resize(index_len(point_data), [F::zero(); N]);
+        let mut swings = Vec::with_capacity(point_data.len());
+        let (mut total_swing, mut total_traction) = (F::zero(), F::zero());
+        for point_data in point_data.iter() {
+            let velocity = self.velocities[point_data.index];
+            let last_acceleration = self.accelerations[point_data.index];
+            let (mut swing, mut traction) = (F::zero(), F::zero());
+            for i in 0..N {
+                let acceleration = point_data.velocity[i] - velocity[i];
+                let difference = acceleration - last_acceleration[i];
+                let sum = acceleration + last_acceleration[i];
+                swing = swing + difference * difference;
+                traction = traction + sum * sum;
+            }
+            let swing = swing.sqrt();
+            swings.push(swing);
+            total_swing = total_swing + swing;
+            total_traction = total_traction + traction.sqrt() * half;
+        }
+
+        if total_swing > F::zero() {
+            let target = self.tolerance * total_traction / total_swing;
+            self.speed = target
+                .min(self.speed * (F::one() + self.max_rise))
+                .min(self.max_speed);
+        }
+        for (point_data, swing) in point_data.iter_mut().zip(swings) {
+            let factor = self.speed / (F::one() + (self.speed * swing).sqrt());
+            let velocity = self.velocities[point_data.index];
+            for i in 0..N {
+                let acceleration = point_data.velocity[i] - velocity[i];
+                let v = (velocity[i] + acceleration * factor) * velocity_decay;
+                point_data.velocity[i] = v;
+                point_data.coord[i] = point_data.coord[i] + v;
+                self.accelerations[point_data.index][i] = acceleration;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.speed = F::one();
+        self.velocities.clear();
+        self.accelerations.clear();
+    }
+}
@@ -0,0 +1,475 @@
+use crate::data::PointData;
+use lazy_static::lazy_static;
+use num::Float;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+lazy_static! {
+    static ref INITIAL_RADIUS: f64 = 10.0;
+    static ref INITIAL_ANGLE_ROLL: f64 = PI * (3.0 - 5.0.sqrt()); // Golden ratio angle
+    static ref INITIAL_ANGLE_YAW: f64 = PI * 20.0 / (9.0 + 221.0.sqrt()); // Markov irrational number
+}
+
+/// Where nodes start before the simulation runs, see [crate::Simulation::set_initial_layout]
+///
+/// Every strategy only depends on its parameters and the nodes, never on the simulation's random
+/// number generator. Nodes are placed by [PointData::index], so nodes added later do not land on
+/// the spots of the earlier ones
+#[derive(Clone, Debug, Default)]
+pub enum InitialLayout<F: Float, const N: usize> {
+    /// Golden-angle spiral (d3 default)
+    #[default]
+    Phyllotaxis,
+    /// Uniform in `[-extent, extent]` on every axis, derived from `seed` and the node id
+    Random { seed: u64, extent: F },
+    /// Golden-angle steps by id on a circle of the first two axes, on a segment when N = 1; a
+    /// node's spot only depends on its id, so batches added later never land on earlier nodes
+    Circle { radius: F },
+    /// Square (cubic...) grid around the origin filled shell by shell in id order, `k³` nodes
+    /// make a full `k x k x k` grid for an odd `k`
+    Grid { spacing: F },
+    /// Coordinates indexed by [PointData::index], nodes without one fall back to [InitialLayout::Phyllotaxis]
+    Coordinates(Vec<[F; N]>),
+    /// Smallest non-trivial eigenvectors of the random walk Laplacian of `links` (pairs of node
+    /// ids), one per axis, scaled so the farthest node is `extent` away on every axis. The
+    /// eigenvectors are computed on every node, nodes already in the simulation are not moved
+    Spectral {
+        links: Vec<(usize, usize)>,
+        extent: F,
+    },
+    /// Uniform in `[-extent, extent]`, hashed from the node id only: the same node always starts
+    /// at the same spot, whatever the other nodes are
+    Hashed { extent: F },
+}
+
+impl<F: Float, const N: usize> InitialLayout<F, N> {
+    /// Overwrite the coordinates of `point_data` and clear their velocity
+    ///
+    /// `placed` are the other nodes of the simulation, they are not moved
+    pub fn place<D>(&self, point_data: &mut [PointData<F, N, D>], placed: &[PointData<F, N, D>]) {
+        let coords = self.coords(point_data, placed);
+        for (point_data, coord) in point_data.iter_mut().zip(coords) {
+            point_data.coord = coord;
+            point_data.velocity = [F::zero(); N];
+        }
+    }
+
+    fn coords<D>(
+        &self,
+        point_data: &[PointData<F, N, D>],
+        placed: &[PointData<F, N, D>],
+    ) -> Vec<[F; N]> {
+        let ids = point_data.iter().map(|point_data| point_data.index);
+        match self {
+            InitialLayout::Phyllotaxis => phyllotaxis(ids),
+            InitialLayout::Random { seed, extent } => {
+                let key = split_mix(*seed);
+                ids.map(|id| {
+                    let mut coord = [F::zero(); N];
+                    for (axis, x) in coord.iter_mut().enumerate() {
+                        let unit = hash_unit(key ^ id as u64, axis);
+                        *x = *extent * F::from(2.0 * unit - 1.0).unwrap();
+                    }
+                    coord
+                })
+                .collect()
+            }
+            InitialLayout::Circle { radius } => ids
+                .map(|k| {
+                    // 每个id转一个黄金角，位置与节点总数无关，不同id的角度永不重合
+                    let angle = k as f64 * *INITIAL_ANGLE_ROLL;
+                    let mut coord = [F::zero(); N];
+                    match N {
+                        1 => {
+                            let unit = (angle / (2.0 * PI)).fract();
+                            coord[0] = F::from(2.0 * unit - 1.0).unwrap() * *radius
+                        }
+                        _ => {
+                            coord[0] = *radius * F::from(angle.cos()).unwrap();
+                            coord[1] = *radius * F::from(angle.sin()).unwrap();
+                        }
+                    }
+                    coord
+                })
+                .collect(),
+            InitialLayout::Grid { spacing } => {
+                let count = point_data
+                    .iter()
+                    .map(|point_data| point_data.index + 1)
+                    .max()
+                    .unwrap_or(0);
+                let cells = grid_cells::<N>(count);
+                ids.map(|id| cells[id].map(|x| *spacing * F::from(x).unwrap()))
+                    .collect()
+            }
+            InitialLayout::Coordinates(coords) => {
+                let fallback = phyllotaxis::<F, N>(ids);
+                point_data
+                    .iter()
+                    .zip(fallback)
+                    .map(|(point_data, fallback)| {
+                        coords.get(point_data.index).copied().unwrap_or(fallback)
+                    })
+                    .collect()
+            }
+            InitialLayout::Spectral { links, extent } => {
+                let ids = placed
+                    .iter()
+                    .chain(point_data)
+                    .map(|point_data| point_data.index)
+                    .collect::<Vec<_>>();
+                let mut coords = spectral(&ids, links, *extent);
+                coords.split_off(placed.len())
+            }
+            InitialLayout::Hashed { extent } => ids
+                .map(|id| {
+                    let mut coord = [F::zero(); N];
+                    for (axis, x) in coord.iter_mut().enumerate() {
+                        *x = *extent * F::from(2.0 * hash_unit(id as u64, axis) - 1.0).unwrap();
+                    }
+                    coord
+                })
+                .collect(),
+        }
+    }
+}
+
+/// First `count` cells of the grid, shell after shell of growing Chebyshev distance to the origin,
+/// every shell in lexicographic order
+fn grid_cells<const N: usize>(count: usize) -> Vec<[i64; N]> {
+    fn visit<const N: usize>(
+        cell: &mut [i64; N],
+        axis: usize,
+        r: i64,
+        on_shell: bool,
+        cells: &mut Vec<[i64; N]>,
+    ) {
+        if axis == N {
+            cells.push(*cell);
+            return;
+        }
+        // 前面的轴都在壳内部时，最后一个轴只能取±r
+        let values = match axis == N - 1 && !on_shell {
+            true => vec![-r, r],
+            false => (-r..=r).collect(),
+        };
+        for x in values {
+            cell[axis] = x;
+            visit(cell, axis + 1, r, on_shell || x.abs() == r, cells);
+        }
+    }
+
+    let mut cells = Vec::with_capacity(count);
+    if count > 0 {
+        cells.push([0; N]);
+    }
+    let mut r = 1;
+    while cells.len() < count {
+        visit(&mut [0; N], 0, r, false, &mut cells);
+        r += 1;
+    }
+    cells.truncate(count);
+    cells
+}
+
+/// SplitMix64 finaliser, fixed so that hashed positions stay the same across builds
+fn split_mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// Number in `[0, 1)` derived from a key (node id) and an axis
+fn hash_unit(key: u64, axis: usize) -> f64 {
+    let hash = split_mix(split_mix(key) ^ axis as u64);
+    (hash >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+/// Power iteration on `(I + D⁻¹A) / 2` (Koren, "On spectral graph drawing"), every vector is kept
+/// D-orthogonal to the constant vector and to the previous axes
+fn spectral<F: Float, const N: usize>(
+    ids: &[usize],
+    links: &[(usize, usize)],
+    extent: F,
+) -> Vec<[F; N]> {
+    const ITERATIONS: usize = 300;
+    let n = ids.len();
+    let index_map: HashMap<usize, usize> = ids
+        .iter()
+        .enumerate()
+        .map(|(position, &id)| (id, position))
+        .collect();
+    let mut adjacency = vec![Vec::new(); n];
+    for (s, t) in links {
+        if let (Some(&s), Some(&t)) = (index_map.get(s), index_map.get(t)) {
+            if s != t {
+                adjacency[s].push(t);
+                adjacency[t].push(s);
+            }
+        }
+    }
+    // 孤立节点视为带自环，度为1
+    let degrees = adjacency
+        .iter()
+        .map(|neighbors| neighbors.len().max(1) as f64)
+        .collect::<Vec<_>>();
+    let d_dot = |a: &[f64], b: &[f64]| (0..n).map(|i| a[i] * b[i] * degrees[i]).sum::<f64>();
+    let orthogonalize = |x: &mut Vec<f64>, basis: &[Vec<f64>]| {
+        for b in basis {
+            let k = d_dot(x, b) / d_dot(b, b);
+            for i in 0..n {
+                x[i] -= k * b[i];
+            }
+        }
+        let norm = d_dot(x, x).sqrt();
+        if norm > 0.0 {
+            x.iter_mut().for_each(|v| *v /= norm);
+        }
+    };
+
+    let mut basis = vec![vec![1.0; n]];
+    for axis in 0..N {
+        let mut x = ids
+            .iter()
+            .map(|&id| hash_unit(id as u64, axis) - 0.5)
+            .collect::<Vec<_>>();
+        orthogonalize(&mut x, &basis);
+        for _ in 0..ITERATIONS {
+            let mut next = (0..n)
+                .map(|i| match adjacency[i].is_empty() {
+                    true => x[i],
+                    false => {
+                        let mean = adjacency[i].iter().map(|&j| x[j]).sum::<f64>() / degrees[i];
+                        (x[i] + mean) / 2.0
+                    }
+                })
+                .collect::<Vec<_>>();
+            orthogonalize(&mut next, &basis);
+            x = next;
+        }
+        basis.push(x);
+    }
+
+    let mut coords = vec![[F::zero(); N]; n];
+    for axis in 0..N {
+        let x = &basis[axis + 1];
+        let max = x.iter().fold(0.0_f64, |m, v| m.max(v.abs()));
+        if max > 0.0 {
+            for i in 0..n {
+                coords[i][axis] = extent * F::from(x[i] / max).unwrap();
+            }
+        }
+    }
+    coords
+}
+
+/// Golden-angle spiral (sphere in 3-D) around the origin, the distance grows with the id so every
+/// node keeps its spot when more nodes are added
+pub(crate) fn phyllotaxis<F: Float, const N: usize>(
+    ids: impl Iterator<Item = usize>,
+) -> Vec<[F; N]> {
+    // N > 3时的低差异序列系数，所有节点共用
+    let alphas = match N {
+        1..=3 => Vec::new(),
+        _ => low_discrepancy_alphas(N),
+    };
+    ids.map(|idx| {
+        let idx_f = idx as f64;
+        let radius = *INITIAL_RADIUS
+            * match N {
+                1 => idx_f,
+                2 => (0.5 + idx_f).sqrt(),
+                3 => (0.5 + idx_f).cbrt(),
+                _ => (0.5 + idx_f).powf(1.0 / N as f64),
+            };
+        let roll_angle = idx_f * *INITIAL_ANGLE_ROLL;
+        let yaw_angle = idx_f * *INITIAL_ANGLE_YAW;
+        let mut coord: [F; N] = [F::zero(); N];
+        match N {
+            1 => coord[0] = F::from(radius).unwrap(),
+            2 => {
+                coord[0] = F::from(radius * roll_angle.cos()).unwrap();
+                coord[1] = F::from(radius * roll_angle.sin()).unwrap()
+            }
+            3 => {
+                coord[0] = F::from(radius * roll_angle.sin() * yaw_angle.cos()).unwrap();
+                coord[1] = F::from(radius * roll_angle.cos()).unwrap();
+                coord[2] = F::from(radius * roll_angle.sin() * yaw_angle.sin()).unwrap();
+            }
+            _ => {
+                // 以R_N序列在立方体中取点，投影到超球面上作为方向，与N <= 3一样半径随id增长
+                let direction = alphas
+                    .iter()
+                    .map(|alpha| 2.0 * (0.5 + alpha * (idx_f + 1.0)).fract() - 1.0)
+                    .collect::<Vec<_>>();
+                let norm = direction.iter().map(|x| x * x).sum::<f64>().sqrt();
+                for i in 0..N {
+                    coord[i] = F::from(radius * direction[i] / norm).unwrap();
+                }
+            }
+        };
+        coord
+    })
+    .collect()
+}
+
+/// Coefficients `φ_d^-k` (k = 1..=d) of the R_d low discrepancy sequence, φ_d being the positive
+/// root of `x^(d+1) = x + 1` (generalised golden ratio)
+fn low_discrepancy_alphas(dimension: usize) -> Vec<f64> {
+    let mut phi = 2.0_f64;
+    for _ in 0..30 {
+        phi = (1.0 + phi).powf(1.0 / (dimension as f64 + 1.0));
+    }
+    (1..=dimension).map(|k| phi.powi(-(k as i32))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InitialLayout;
+    use crate::data::PointData;
+    use crate::Simulation;
+
+    fn nodes(ids: impl Iterator<Item = usize>) -> Vec<PointData<f64, 2, ()>> {
+        ids.map(|id| PointData::from_data((), [0.0; 2], id))
+            .collect()
+    }
+
+    fn coords(layout: &InitialLayout<f64, 2>, ids: impl Iterator<Item = usize>) -> Vec<[f64; 2]> {
+        let mut point_data = nodes(ids);
+        layout.place(&mut point_data, &[]);
+        point_data
+            .iter()
+            .map(|point_data| point_data.coord)
+            .collect()
+    }
+
+    #[test]
+    fn test_initial_layouts() {
+        // 默认布局与Simulation的初始化一致
+        let simulation: Simulation<f64, 2, i32> = Simulation::from_data(Vec::from_iter(0..10));
+        let phyllotaxis = coords(&InitialLayout::Phyllotaxis, 0..10);
+        for (point_data, coord) in simulation.force_point_data.iter().zip(phyllotaxis.iter()) {
+            assert_eq!(point_data.coord, *coord);
+        }
+
+        let random = InitialLayout::Random {
+            seed: 7,
+            extent: 50.0,
+        };
+        assert_eq!(coords(&random, 0..20), coords(&random, 0..20));
+        assert!(coords(&random, 0..20)
+            .iter()
+            .all(|c| c.iter().all(|x| x.abs() <= 50.0)));
+
+        for [x, y] in coords(&InitialLayout::Circle { radius: 30.0 }, 0..12) {
+            assert!((x.hypot(y) - 30.0).abs() < 1e-9);
+        }
+
+        // 3 x 3的网格由中心开始填充
+        let grid = coords(&InitialLayout::Grid { spacing: 10.0 }, 0..9);
+        assert_eq!(grid[0], [0.0, 0.0]);
+        assert_eq!(grid[1], [-10.0, -10.0]);
+        assert_eq!(grid[8], [10.0, 10.0]);
+        assert!(grid.iter().all(|c| c.iter().all(|x| x.abs() <= 10.0)));
+
+        let given = InitialLayout::Coordinates(vec![[1.0, 2.0], [3.0, 4.0]]);
+        let placed = coords(&given, 0..3);
+        assert_eq!(&placed[..2], &[[1.0, 2.0], [3.0, 4.0]]);
+        assert_eq!(placed[2], phyllotaxis[2]);
+    }
+
+    #[test]
+    fn test_add_nodes_one_at_a_time() {
+        let layouts = [
+            InitialLayout::Phyllotaxis,
+            InitialLayout::Random {
+                seed: 1,
+                extent: 100.0,
+            },
+            InitialLayout::Circle { radius: 100.0 },
+            InitialLayout::Grid { spacing: 10.0 },
+            InitialLayout::Hashed { extent: 100.0 },
+        ];
+        // 逐个加入，以及多个节点一批加入
+        let singles = Vec::from_iter((0..30).map(|data| vec![data]));
+        let batches = vec![
+            vec![0, 1],
+            vec![2, 3],
+            vec![4, 5, 6],
+            Vec::from_iter(7..12),
+            vec![12, 13, 14],
+        ];
+        for (layout, batches) in layouts
+            .iter()
+            .flat_map(|layout| [(layout, &singles), (layout, &batches)])
+        {
+            let mut simulation: Simulation<f64, 2, i32> = Simulation::default();
+            simulation.set_initial_layout(layout.clone());
+            for batch in batches {
+                simulation.add_nodes(batch.clone());
+            }
+            let placed = simulation
+                .force_point_data
+                .iter()
+                .map(|point_data| point_data.coord)
+                .collect::<Vec<_>>();
+            for (k, a) in placed.iter().enumerate() {
+                for b in placed[k + 1..].iter() {
+                    assert!((a[0] - b[0]).hypot(a[1] - b[1]) > 1e-6, "{:?}", layout);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_hashed_layout() {
+        let hashed = InitialLayout::Hashed { extent: 100.0 };
+        // 位置只取决于id，与其它节点和顺序无关
+        let all = coords(&hashed, 0..50);
+        let some = coords(&hashed, [40, 3, 17].into_iter());
+        assert_eq!(some, vec![all[40], all[3], all[17]]);
+        assert_ne!(all[0], all[1]);
+        // 哈希固定，跨会话、跨版本保持不变
+        assert_eq!(all[0], [30.48969727480644, -93.19765973913073]);
+
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::default();
+        simulation.set_initial_layout(hashed);
+        simulation.set_data(Vec::from_iter(0..30));
+        simulation.add_nodes(Vec::from_iter(30..50));
+        for point_data in simulation.force_point_data.iter() {
+            assert_eq!(point_data.coord, all[point_data.index]);
+        }
+        simulation.force_point_data[0].coord = [0.0, 0.0];
+        simulation.reset_positions();
+        assert_eq!(simulation.force_point_data[0].coord, all[0]);
+    }
+
+    #[test]
+    fn test_spectral_layout() {
+        let links = (1..20).map(|i| (i - 1, i)).collect::<Vec<_>>();
+        let spectral = InitialLayout::Spectral {
+            links,
+            extent: 100.0,
+        };
+        let placed = coords(&spectral, 0..20);
+        // 路径图的第一个特征向量沿路径单调
+        let increasing = placed.windows(2).all(|pair| pair[0][0] < pair[1][0]);
+        let decreasing = placed.windows(2).all(|pair| pair[0][0] > pair[1][0]);
+        assert!(increasing || decreasing);
+        let max = placed.iter().fold(0.0_f64, |m, c| m.max(c[0].abs()));
+        assert!((max - 100.0).abs() < 1e-9);
+        assert_eq!(placed, coords(&spectral, 0..20));
+
+        // 之后加入的节点按整张图计算，已有节点不移动
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::default();
+        simulation.set_initial_layout(spectral);
+        simulation.set_data(Vec::from_iter(0..10));
+        let first = simulation.force_point_data[0].coord;
+        simulation.add_nodes(Vec::from_iter(10..20));
+        assert_eq!(simulation.force_point_data[0].coord, first);
+        for point_data in simulation.force_point_data[10..].iter() {
+            assert_eq!(point_data.coord, placed[point_data.index]);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub(crate) mod initial_layout;
+mod multilevel;
+mod stress_majorization;
+
+pub use initial_layout::InitialLayout;
+pub use multilevel::MultilevelLayout;
+pub use stress_majorization::StressMajorization;
@@ -0,0 +1,330 @@
+use crate::data::PointData;
+use crate::force::{LinkForce, NBodyForce};
+use crate::Simulation;
+use num::Float;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::min;
+use std::collections::HashMap;
+
+/// Links between positions of one level, the payload is the number of original links merged
+type WeightedLinks<F> = Vec<((usize, usize), F)>;
+
+/// One level of the coarsening hierarchy
+struct Level<F: Float> {
+    /// 每个节点代表的原始节点数
+    weights: Vec<usize>,
+    links: WeightedLinks<F>,
+    /// 本层节点下标 -> 其在更粗一层中的下标
+    parents: Vec<usize>,
+}
+
+/// Multilevel layout for large graphs (after FM³ / sfdp)
+///
+/// The graph is coarsened by heavy edge matching until it has at most `coarsest_size` nodes, the
+/// coarsest graph is laid out with [LinkForce] and [NBodyForce], then positions are prolonged to
+/// the finer level and refined there, level by level. Merged nodes repel in proportion to the
+/// number of nodes they stand for and merged links keep their count as [LinkForce] payload
+#[derive(Clone, Copy, Debug)]
+pub struct MultilevelLayout<F: Float> {
+    pub edge_length: F,
+    /// 节点数不超过该值时停止粗化
+    pub coarsest_size: usize,
+    /// Ticks of the force simulation on every level
+    pub ticks_per_level: usize,
+    /// Alpha the refinement of a finer level starts from, the coarsest level starts from 1
+    pub refine_alpha: F,
+    /// NBody strength of a single original node
+    pub repulsion: F,
+    pub seed: u64,
+}
+
+impl<F: Float> Default for MultilevelLayout<F> {
+    fn default() -> Self {
+        MultilevelLayout {
+            edge_length: F::from(30.0).unwrap(),
+            coarsest_size: 50,
+            ticks_per_level: 300,
+            refine_alpha: F::from(0.3).unwrap(),
+            repulsion: F::from(-30.0).unwrap(),
+            seed: 0,
+        }
+    }
+}
+
+impl<F: Float + Send + Sync + 'static> MultilevelLayout<F> {
+    /// Move the nodes of `simulation` to the multilevel layout and clear their velocity,
+    /// returns the number of levels
+    pub fn seed_simulation<const N: usize, const N2: usize, D: 'static>(
+        &self,
+        simulation: &mut Simulation<F, N, D>,
+        links: &[(usize, usize)],
+    ) -> usize {
+        self.layout::<N, N2, D>(&mut simulation.force_point_data, links)
+    }
+
+    /// Lay out `point_data` in place, `links` are pairs of node ids; returns the number of levels
+    ///
+    /// The current coordinates are ignored, the result only depends on the graph and `seed`
+    pub fn layout<const N: usize, const N2: usize, D>(
+        &self,
+        point_data: &mut [PointData<F, N, D>],
+        links: &[(usize, usize)],
+    ) -> usize {
+        if point_data.is_empty() {
+            return 0;
+        }
+        let index_map = PointData::index_map(point_data);
+        let finest = links
+            .iter()
+            .filter_map(|(s, t)| Some((*index_map.get(s)?, *index_map.get(t)?)))
+            .map(|link| (link, F::one()))
+            .collect::<Vec<_>>();
+        let levels = self.coarsen(vec![1; point_data.len()], &finest);
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut coords: Option<Vec<[F; N]>> = None;
+        for (depth, level) in levels.iter().enumerate().rev() {
+            let alpha = match coords {
+                None => F::one(),
+                Some(_) => self.refine_alpha,
+            };
+            // 由更粗一层延拓：子节点放在父节点附近，加上少量随机偏移以分开合并的节点
+            let start = coords.map(|coarse| {
+                let parents = &levels[depth].parents;
+                let offset = self.edge_length * F::from(0.1).unwrap();
+                parents
+                    .iter()
+                    .map(|&parent| {
+                        let mut coord = coarse[parent];
+                        for x in coord.iter_mut() {
+                            *x = *x + offset * F::from(rng.gen_range(-1.0..1.0)).unwrap();
+                        }
+                        coord
+                    })
+                    .collect::<Vec<_>>()
+            });
+            coords = Some(self.refine::<N, N2>(level, start, alpha));
+        }
+
+        for (point_data, coord) in point_data.iter_mut().zip(coords.unwrap()) {
+            point_data.coord = coord;
+            point_data.velocity = [F::zero(); N];
+        }
+        levels.len()
+    }
+
+    /// Finest level first; the parents of the coarsest level are never used
+    fn coarsen(&self, weights: Vec<usize>, links: &[((usize, usize), F)]) -> Vec<Level<F>> {
+        let mut levels = Vec::new();
+        let mut weights = weights;
+        let mut links = merge_links(links.iter().copied());
+        loop {
+            let n = weights.len();
+            if n <= self.coarsest_size {
+                break;
+            }
+            let (parents, count) = heavy_edge_matching(&weights, &links);
+            // 匹配几乎不能再缩小图（如星形图）时停止
+            if count * 10 > n * 9 {
+                break;
+            }
+            let mut coarse_weights = vec![0; count];
+            for (position, &parent) in parents.iter().enumerate() {
+                coarse_weights[parent] += weights[position];
+            }
+            let coarse_links = merge_links(
+                links
+                    .iter()
+                    .map(|&((s, t), weight)| ((parents[s], parents[t]), weight)),
+            );
+            levels.push(Level {
+                weights: std::mem::replace(&mut weights, coarse_weights),
+                links: std::mem::replace(&mut links, coarse_links),
+                parents,
+            });
+        }
+        levels.push(Level {
+            weights,
+            links,
+            parents: Vec::new(),
+        });
+        levels
+    }
+
+    /// Run the force simulation on one level, starting from the phyllotaxis layout if `start` is None
+    fn refine<const N: usize, const N2: usize>(
+        &self,
+        level: &Level<F>,
+        start: Option<Vec<[F; N]>>,
+        alpha: F,
+    ) -> Vec<[F; N]> {
+        let mut simulation = Simulation::<F, N, usize>::with_seed(self.seed);
+        simulation.set_data(level.weights.clone());
+        if let Some(start) = start {
+            for (point_data, coord) in simulation.force_point_data.iter_mut().zip(start) {
+                point_data.coord = coord;
+            }
+        }
+
+        // 合并后的节点在N维中占据的尺寸按weight^(1/N)增长
+        let size = |weight: usize| {
+            F::from(weight)
+                .unwrap()
+                .powf(F::one() / F::from(N).unwrap())
+        };
+        let distances = level
+            .links
+            .iter()
+            .map(|&((s, t), _)| {
+                self.edge_length * (size(level.weights[s]) + size(level.weights[t]))
+                    / F::from(2).unwrap()
+            })
+            .collect();
+        let mut link_force = LinkForce::<F, N, usize, F>::new(
+            level.links.clone(),
+            |link, count| {
+                let degree = min(count[link.source], count[link.target]);
+                (*link.data() / F::from(degree).unwrap()).min(F::one())
+            },
+            |_, _| F::zero(),
+            1,
+        );
+        link_force.set_distances(distances);
+        let repulsion = self.repulsion;
+        let mut nbody_force = NBodyForce::<F, N, N2, usize>::default();
+        nbody_force
+            .set_strength_fn(move |point_data, _| repulsion * F::from(point_data.data).unwrap());
+        simulation.add_force(String::from("link"), Box::new(link_force));
+        simulation.add_force(String::from("n-body"), Box::new(nbody_force));
+
+        // 在ticks_per_level次tick内由alpha冷却到alpha_min
+        let ticks = F::from(self.ticks_per_level.max(1)).unwrap();
+        simulation.set_alpha(alpha);
+        simulation
+            .set_alpha_decay(F::one() - (simulation.alpha_min() / alpha).powf(F::one() / ticks));
+        simulation.run_until_stable(self.ticks_per_level);
+        simulation
+            .force_point_data
+            .iter()
+            .map(|point_data| point_data.coord)
+            .collect()
+    }
+}
+
+/// Drop self loops and sum the weights of parallel links, sorted to keep the result deterministic
+fn merge_links<F: Float>(links: impl Iterator<Item = ((usize, usize), F)>) -> WeightedLinks<F> {
+    let mut merged: HashMap<(usize, usize), F> = HashMap::new();
+    for ((s, t), weight) in links {
+        if s != t {
+            let entry = merged.entry((s.min(t), s.max(t))).or_insert(F::zero());
+            *entry = *entry + weight;
+        }
+    }
+    let mut merged = merged.into_iter().collect::<Vec<_>>();
+    merged.sort_by_key(|&(link, _)| link);
+    merged
+}
+
+/// Match every node with its unmatched neighbour of heaviest link, lighter nodes first,
+/// returns the parent of every node and the number of parents
+fn heavy_edge_matching<F: Float>(
+    weights: &[usize],
+    links: &[((usize, usize), F)],
+) -> (Vec<usize>, usize) {
+    let mut adjacency = vec![Vec::new(); weights.len()];
+    for &((s, t), weight) in links {
+        adjacency[s].push((t, weight));
+        adjacency[t].push((s, weight));
+    }
+    let mut order = (0..weights.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&position| weights[position]);
+
+    let mut parents = vec![usize::MAX; weights.len()];
+    let mut count = 0;
+    for position in order {
+        if parents[position] != usize::MAX {
+            continue;
+        }
+        let mut best: Option<(usize, F)> = None;
+        for &(neighbor, weight) in adjacency[position].iter() {
+            if parents[neighbor] != usize::MAX {
+                continue;
+            }
+            // 边权相同时优先合并更轻的节点，避免产生过重的节点
+            let better = match best {
+                None => true,
+                Some((other, best_weight)) => {
+                    weight > best_weight
+                        || (weight == best_weight && weights[neighbor] < weights[other])
+                }
+            };
+            if better {
+                best = Some((neighbor, weight));
+            }
+        }
+        parents[position] = count;
+        if let Some((neighbor, _)) = best {
+            parents[neighbor] = count;
+        }
+        count += 1;
+    }
+    (parents, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultilevelLayout;
+    use crate::force::{LinkForce, NBodyForce};
+    use crate::metrics::edge_crossings;
+    use crate::Simulation;
+
+    /// side x side的网格图
+    fn grid(side: usize) -> Vec<(usize, usize)> {
+        let mut links = Vec::new();
+        for i in 0..side * side {
+            if i % side + 1 < side {
+                links.push((i, i + 1));
+            }
+            if i + side < side * side {
+                links.push((i, i + side));
+            }
+        }
+        links
+    }
+
+    #[test]
+    fn test_multilevel_layout() {
+        let links = grid(20);
+        let layout = MultilevelLayout {
+            coarsest_size: 20,
+            ..MultilevelLayout::default()
+        };
+        let mut first: Simulation<f64, 2, i32> = Simulation::from_data(Vec::from_iter(0..400));
+        let levels = layout.seed_simulation::<2, 4, i32>(&mut first, &links);
+        assert!(levels > 2);
+        let mut second: Simulation<f64, 2, i32> = Simulation::from_data(Vec::from_iter(0..400));
+        layout.seed_simulation::<2, 4, i32>(&mut second, &links);
+        for (a, b) in first
+            .force_point_data
+            .iter()
+            .zip(second.force_point_data.iter())
+        {
+            assert_eq!(a.coord, b.coord);
+        }
+
+        // 直接模拟的网格会缠绕，多层布局则更少
+        let mut single: Simulation<f64, 2, i32> = Simulation::with_seed(0);
+        single.set_data(Vec::from_iter(0..400));
+        let mut link_force: LinkForce<f64, 2, i32> = LinkForce::default();
+        link_force.set_links(links.clone());
+        single.add_force(String::from("link"), Box::new(link_force));
+        single.add_force(
+            String::from("n-body"),
+            Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+        );
+        single.run_until_stable(300);
+        let multilevel = edge_crossings(&first.force_point_data, &links);
+        assert!(multilevel < edge_crossings(&single.force_point_data, &links));
+    }
+}
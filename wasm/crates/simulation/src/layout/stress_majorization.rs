@@ -0,0 +1,182 @@
+use crate::data::PointData;
+use crate::metrics::graph_distances;
+use crate::Simulation;
+use num::Float;
+
+/// Stress majorization (SMACOF) on graph shortest-path distances
+///
+/// Minimises `Σ wij (|xi - xj| - dij)²` with `wij = dij⁻²`, `dij` being the number of links between
+/// two nodes times `edge_length`. Every iteration is deterministic and never increases the stress,
+/// so the same input always gives the same layout
+#[derive(Clone, Copy, Debug)]
+pub struct StressMajorization<F: Float> {
+    pub edge_length: F,
+    pub max_iterations: usize,
+    /// 相对stress下降小于tolerance时停止
+    pub tolerance: F,
+}
+
+impl<F: Float> Default for StressMajorization<F> {
+    fn default() -> Self {
+        StressMajorization {
+            edge_length: F::from(30.0).unwrap(),
+            max_iterations: 300,
+            tolerance: F::from(1e-4).unwrap(),
+        }
+    }
+}
+
+impl<F: Float + 'static> StressMajorization<F> {
+    pub fn new(edge_length: F, max_iterations: usize, tolerance: F) -> StressMajorization<F> {
+        StressMajorization {
+            edge_length,
+            max_iterations,
+            tolerance,
+        }
+    }
+
+    /// Lay out fresh nodes for `data`, ids are assigned like [Simulation::set_data]
+    pub fn layout_data<const N: usize, D: 'static>(
+        &self,
+        data: Vec<D>,
+        links: &[(usize, usize)],
+    ) -> Vec<PointData<F, N, D>> {
+        let mut point_data = Simulation::<F, N, D>::init_point_data(data, 0);
+        self.layout(&mut point_data, links);
+        point_data
+    }
+
+    /// Move the nodes of `simulation` to the stress majorization layout and clear their velocity,
+    /// so that the force simulation only has to refine it
+    pub fn seed_simulation<const N: usize, D: 'static>(
+        &self,
+        simulation: &mut Simulation<F, N, D>,
+        links: &[(usize, usize)],
+    ) -> usize {
+        let iterations = self.layout(&mut simulation.force_point_data, links);
+        for point_data in simulation.force_point_data.iter_mut() {
+            point_data.velocity = [F::zero(); N];
+        }
+        iterations
+    }
+
+    /// Lay out `point_data` in place starting from its current coordinates, `links` are pairs of
+    /// node ids; returns the number of iterations run
+    ///
+    /// Nodes in different components are kept one link further apart than the longest distance
+    pub fn layout<const N: usize, D>(
+        &self,
+        point_data: &mut [PointData<F, N, D>],
+        links: &[(usize, usize)],
+    ) -> usize {
+        let n = point_data.len();
+        if n < 2 {
+            return 0;
+        }
+        let hops = graph_distances(point_data, links);
+        let longest = hops.iter().flatten().flatten().copied().max().unwrap_or(0);
+        let distances: Vec<Vec<F>> = hops
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|d| F::from(d.unwrap_or(longest + 1)).unwrap() * self.edge_length)
+                    .collect()
+            })
+            .collect();
+
+        let mut last_stress = self.stress(point_data, &distances);
+        for iteration in 0..self.max_iterations {
+            // Gauss-Seidel形式的Guttman变换，按固定顺序逐个节点更新
+            for i in 0..n {
+                let mut numerator = [F::zero(); N];
+                let mut denominator = F::zero();
+                for j in 0..n {
+                    if i == j {
+                        continue;
+                    }
+                    let d = distances[i][j];
+                    let w = F::one() / (d * d);
+                    let (xi, xj) = (point_data[i].coord, point_data[j].coord);
+                    let norm = (0..N)
+                        .map(|k| xi[k] - xj[k])
+                        .fold(F::zero(), |s, x| s + x * x)
+                        .sqrt();
+                    for k in 0..N {
+                        let pull = match norm > F::zero() {
+                            true => d * (xi[k] - xj[k]) / norm,
+                            false => F::zero(),
+                        };
+                        numerator[k] = numerator[k] + w * (xj[k] + pull);
+                    }
+                    denominator = denominator + w;
+                }
+                for k in 0..N {
+                    point_data[i].coord[k] = numerator[k] / denominator;
+                }
+            }
+            let stress = self.stress(point_data, &distances);
+            if last_stress - stress <= self.tolerance * last_stress {
+                return iteration + 1;
+            }
+            last_stress = stress;
+        }
+        self.max_iterations
+    }
+
+    /// Weighted stress `Σ wij (|xi - xj| - dij)²`
+    fn stress<const N: usize, D>(
+        &self,
+        point_data: &[PointData<F, N, D>],
+        distances: &[Vec<F>],
+    ) -> F {
+        let mut stress = F::zero();
+        for i in 0..point_data.len() {
+            for j in i + 1..point_data.len() {
+                let d = distances[i][j];
+                let norm = (0..N)
+                    .map(|k| point_data[i].coord[k] - point_data[j].coord[k])
+                    .fold(F::zero(), |s, x| s + x * x)
+                    .sqrt();
+                stress = stress + (norm - d) * (norm - d) / (d * d);
+            }
+        }
+        stress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StressMajorization;
+    use crate::force::NBodyForce;
+    use crate::metrics::stress;
+    use crate::Simulation;
+
+    #[test]
+    fn test_stress_majorization() {
+        let links = (1..6).map(|i| (i - 1, i)).collect::<Vec<_>>();
+        let layout = StressMajorization::new(10.0, 500, 1e-9);
+        let first = layout.layout_data::<2, ()>(vec![(); 6], &links);
+        // 路径图可以在直线上完全还原图距离
+        assert!(stress(&first, &links, 10.0) < 1e-3);
+        let second = layout.layout_data::<2, ()>(vec![(); 6], &links);
+        for (a, b) in first.iter().zip(second.iter()) {
+            assert_eq!(a.coord, b.coord);
+        }
+    }
+
+    #[test]
+    fn test_seed_simulation() {
+        let links = (1..30).map(|i| (i / 2, i)).collect::<Vec<_>>();
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(Vec::from_iter(0..30));
+        let before = stress(&simulation.force_point_data, &links, 30.0);
+        let iterations = StressMajorization::default().seed_simulation(&mut simulation, &links);
+        assert!(iterations > 0);
+        let after = stress(&simulation.force_point_data, &links, 30.0);
+        assert!(after < before);
+        simulation.add_force(
+            String::from("n-body"),
+            Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+        );
+        simulation.tick();
+    }
+}
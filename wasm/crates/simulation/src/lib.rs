@@ -2,7 +2,12 @@
 #![feature(ptr_metadata)]
 extern crate core;
 
+pub mod constraint;
+pub mod cooling;
 pub mod data;
 pub mod force;
+pub mod integrator;
+pub mod layout;
+pub mod metrics;
 mod simulation;
-pub use simulation::Simulation;
+pub use simulation::{Simulation, StopReason, TickStats, Ticks};
@@ -0,0 +1,267 @@
+use crate::data::PointData;
+use crate::Simulation;
+use num::Float;
+use std::collections::VecDeque;
+
+/// Quality of a layout snapshot, see [LayoutMetrics::measure]
+#[derive(Clone, Copy, Debug)]
+pub struct LayoutMetrics<F: Float> {
+    /// See [stress]
+    pub stress: F,
+    pub edge_length_variance: F,
+    /// Only counted for 2-D layouts
+    pub edge_crossings: Option<usize>,
+    /// Smallest distance between two nodes
+    pub min_separation: F,
+    /// Pairs of nodes closer than the sum of their radius
+    pub overlaps: usize,
+    pub kinetic_energy: F,
+}
+
+impl<F: Float> LayoutMetrics<F> {
+    /// `links` are pairs of node ids, `edge_length` is the desired length of a single link
+    ///
+    /// Stress and node separation compare every pair of nodes, so this is O(N²)
+    pub fn measure<const N: usize, D>(
+        point_data: &[PointData<F, N, D>],
+        links: &[(usize, usize)],
+        edge_length: F,
+    ) -> LayoutMetrics<F> {
+        LayoutMetrics {
+            stress: stress(point_data, links, edge_length),
+            edge_length_variance: edge_length_variance(point_data, links),
+            edge_crossings: match N {
+                2 => Some(edge_crossings(point_data, links)),
+                _ => None,
+            },
+            min_separation: min_separation(point_data),
+            overlaps: overlap_count(point_data),
+            kinetic_energy: kinetic_energy(point_data),
+        }
+    }
+
+    /// Measure the current state of `simulation`
+    pub fn from_simulation<const N: usize, D>(
+        simulation: &Simulation<F, N, D>,
+        links: &[(usize, usize)],
+        edge_length: F,
+    ) -> LayoutMetrics<F> {
+        Self::measure(&simulation.force_point_data, links, edge_length)
+    }
+}
+
+fn distance<F: Float, const N: usize>(a: &[F; N], b: &[F; N]) -> F {
+    (0..N)
+        .map(|i| a[i] - b[i])
+        .fold(F::zero(), |s, x| s + x * x)
+        .sqrt()
+}
+
+/// Links as pairs of positions in `point_data`, links with a missing endpoint are dropped
+fn link_positions<F: Float, const N: usize, D>(
+    point_data: &[PointData<F, N, D>],
+    links: &[(usize, usize)],
+) -> Vec<(usize, usize)> {
+    let index_map = PointData::index_map(point_data);
+    links
+        .iter()
+        .filter_map(|(s, t)| Some((*index_map.get(s)?, *index_map.get(t)?)))
+        .collect()
+}
+
+/// Number of links between every pair of nodes (BFS from every node), None if disconnected
+///
+/// Both dimensions follow the order of `point_data`
+pub fn graph_distances<F: Float, const N: usize, D>(
+    point_data: &[PointData<F, N, D>],
+    links: &[(usize, usize)],
+) -> Vec<Vec<Option<usize>>> {
+    let mut adjacency = vec![Vec::new(); point_data.len()];
+    for (s, t) in link_positions(point_data, links) {
+        adjacency[s].push(t);
+        adjacency[t].push(s);
+    }
+    let mut distances = Vec::with_capacity(point_data.len());
+    let mut queue = VecDeque::new();
+    for source in 0..point_data.len() {
+        let mut row = vec![None; point_data.len()];
+        row[source] = Some(0);
+        queue.push_back(source);
+        while let Some(node) = queue.pop_front() {
+            let d = row[node].unwrap() + 1;
+            for &next in adjacency[node].iter() {
+                if row[next].is_none() {
+                    row[next] = Some(d);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances.push(row);
+    }
+    distances
+}
+
+/// `Σ ((|xi - xj| - dij) / dij)²` over connected pairs, `dij` is the graph distance times
+/// `edge_length`; 0 means the layout reproduces the graph distances exactly
+pub fn stress<F: Float, const N: usize, D>(
+    point_data: &[PointData<F, N, D>],
+    links: &[(usize, usize)],
+    edge_length: F,
+) -> F {
+    let distances = graph_distances(point_data, links);
+    let mut stress = F::zero();
+    for i in 0..point_data.len() {
+        for j in i + 1..point_data.len() {
+            if let Some(d) = distances[i][j] {
+                let d = F::from(d).unwrap() * edge_length;
+                let e = (distance(&point_data[i].coord, &point_data[j].coord) - d) / d;
+                stress = stress + e * e;
+            }
+        }
+    }
+    stress
+}
+
+pub fn edge_length_variance<F: Float, const N: usize, D>(
+    point_data: &[PointData<F, N, D>],
+    links: &[(usize, usize)],
+) -> F {
+    let lengths = link_positions(point_data, links)
+        .into_iter()
+        .map(|(s, t)| distance(&point_data[s].coord, &point_data[t].coord))
+        .collect::<Vec<_>>();
+    if lengths.is_empty() {
+        return F::zero();
+    }
+    let n = F::from(lengths.len()).unwrap();
+    let mean = lengths.iter().fold(F::zero(), |s, &x| s + x) / n;
+    lengths
+        .iter()
+        .fold(F::zero(), |s, &x| s + (x - mean) * (x - mean))
+        / n
+}
+
+/// Sign of the cross product of `b - a` and `c - a`
+fn orientation<F: Float>(a: (F, F), b: (F, F), c: (F, F)) -> i8 {
+    let cross = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+    if cross > F::zero() {
+        1
+    } else if cross < F::zero() {
+        -1
+    } else {
+        0
+    }
+}
+
+/// Pairs of links whose segments properly cross on the plane of the first two axes,
+/// links sharing an endpoint never count
+pub fn edge_crossings<F: Float, const N: usize, D>(
+    point_data: &[PointData<F, N, D>],
+    links: &[(usize, usize)],
+) -> usize {
+    assert!(N >= 2, "edge crossings need at least 2 dimensions");
+    let xy = |position: usize| (point_data[position].coord[0], point_data[position].coord[1]);
+    let links = link_positions(point_data, links);
+    let mut crossings = 0;
+    for (k, &(a, b)) in links.iter().enumerate() {
+        for &(c, d) in links[k + 1..].iter() {
+            if a == c || a == d || b == c || b == d {
+                continue;
+            }
+            let (pa, pb, pc, pd) = (xy(a), xy(b), xy(c), xy(d));
+            if orientation(pa, pb, pc) * orientation(pa, pb, pd) < 0
+                && orientation(pc, pd, pa) * orientation(pc, pd, pb) < 0
+            {
+                crossings += 1;
+            }
+        }
+    }
+    crossings
+}
+
+/// Smallest distance between two nodes, infinity for less than 2 nodes
+pub fn min_separation<F: Float, const N: usize, D>(point_data: &[PointData<F, N, D>]) -> F {
+    let mut min = F::infinity();
+    for i in 0..point_data.len() {
+        for j in i + 1..point_data.len() {
+            min = min.min(distance(&point_data[i].coord, &point_data[j].coord));
+        }
+    }
+    min
+}
+
+/// Pairs of nodes closer than the sum of their [PointData::radius]
+pub fn overlap_count<F: Float, const N: usize, D>(point_data: &[PointData<F, N, D>]) -> usize {
+    let mut count = 0;
+    for i in 0..point_data.len() {
+        for j in i + 1..point_data.len() {
+            let d = distance(&point_data[i].coord, &point_data[j].coord);
+            if d < point_data[i].radius + point_data[j].radius {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Sum of `|v|² / 2` over every node
+pub fn kinetic_energy<F: Float, const N: usize, D>(point_data: &[PointData<F, N, D>]) -> F {
+    let half = F::from(0.5).unwrap();
+    point_data.iter().fold(F::zero(), |s, point_data| {
+        s + point_data
+            .velocity
+            .iter()
+            .fold(F::zero(), |s, &x| s + x * x)
+            * half
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{graph_distances, LayoutMetrics};
+    use crate::data::PointData;
+
+    fn square() -> Vec<PointData<f64, 2, ()>> {
+        [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]
+            .into_iter()
+            .enumerate()
+            .map(|(i, coord)| PointData::from_data((), coord, i))
+            .collect()
+    }
+
+    #[test]
+    fn test_graph_distances() {
+        let distances = graph_distances(&square(), &[(0, 1), (1, 2), (5, 3)]);
+        assert_eq!(distances[0], vec![Some(0), Some(1), Some(2), None]);
+        assert_eq!(distances[3], vec![None, None, None, Some(0)]);
+    }
+
+    #[test]
+    fn test_layout_metrics() {
+        let mut point_data = square();
+        let cycle = [(0, 1), (1, 2), (2, 3), (3, 0)];
+        let metrics = LayoutMetrics::measure(&point_data, &cycle, 1.0);
+        // 对角线距离为sqrt(2)，图距离为2
+        let e = (2_f64.sqrt() - 2.0) / 2.0;
+        assert!((metrics.stress - 2.0 * e * e).abs() < 1e-12);
+        assert_eq!(metrics.edge_length_variance, 0.0);
+        assert_eq!(metrics.edge_crossings, Some(0));
+        assert_eq!(metrics.min_separation, 1.0);
+        assert_eq!(metrics.overlaps, 0);
+        assert_eq!(metrics.kinetic_energy, 0.0);
+
+        let diagonals = [(0, 2), (1, 3), (0, 1)];
+        assert_eq!(
+            LayoutMetrics::measure(&point_data, &diagonals, 1.0).edge_crossings,
+            Some(1)
+        );
+
+        point_data[0].radius = 0.6;
+        point_data[1].radius = 0.6;
+        point_data[2].velocity = [3.0, 4.0];
+        let metrics = LayoutMetrics::measure(&point_data, &[(0, 1), (1, 2), (0, 2)], 1.0);
+        assert_eq!(metrics.overlaps, 1);
+        assert_eq!(metrics.kinetic_energy, 12.5);
+        assert!(metrics.edge_length_variance > 0.0);
+    }
+}
@@ -1,22 +1,63 @@
+use crate::constraint::Constraint;
+use crate::cooling::{CoolingSchedule, ExponentialCooling};
 use crate::data::PointData;
-use crate::force::ForceSimulate;
-use lazy_static::lazy_static;
+use crate::force::{Boundary, BoundaryMode, ForceContext, ForceSimulate, TickContext};
+use crate::integrator::{EulerIntegrator, Integrator};
+use crate::layout::initial_layout::phyllotaxis;
+use crate::layout::InitialLayout;
 use num::Float;
-use std::collections::HashMap;
-use std::f64::consts::PI;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
 
-lazy_static! {
-    static ref INITIAL_RADIUS: f64 = 10.0;
-    static ref INITIAL_ANGLE_ROLL: f64 = PI * (3.0 - 5.0.sqrt()); // Golden ratio angle
-    static ref INITIAL_ANGLE_YAW: f64 = PI * 20.0 / (9.0 + 221.0.sqrt()); // Markov irrational number
+/// Statistics of a single [Simulation::tick]
+#[derive(Clone, Copy, Debug)]
+pub struct TickStats<F: Float> {
+    pub alpha: F,
+    /// Sum of `|v|² / 2` over every node after integration
+    pub kinetic_energy: F,
+    /// Largest distance a node moved during the tick
+    pub max_displacement: F,
+}
+
+/// Why the simulation stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// alpha fell below alpha_min
+    AlphaMin,
+    /// Kinetic energy fell below [Simulation::set_energy_threshold]
+    KineticEnergy,
+    /// Max displacement fell below [Simulation::set_displacement_threshold]
+    Displacement,
+    /// [Simulation::run_until_stable] ran out of ticks before the layout converged
+    MaxTicks,
+}
+
+/// [Simulation::on_tick]注册的回调
+type TickListener<F> = Box<dyn FnMut(&TickStats<F>)>;
+/// [Simulation::on_end]注册的回调
+type EndListener = Box<dyn FnMut(StopReason)>;
+
+/// 已注册的力
+struct RegisteredForce<F: Float, const N: usize, D> {
+    name: String,
+    priority: i32,
+    force: Box<dyn ForceSimulate<F, N, D>>,
 }
 
 /// 物理模拟
 pub struct Simulation<F: Float, const N: usize, D> {
     /// 真实数据列表，用以初始化，初始化后将被封装在ForceNode节点中持有引用
     pub force_point_data: Vec<PointData<F, N, D>>,
-    /// 全部物理力
-    pub forces: HashMap<String, Box<dyn ForceSimulate<F, N, D>>>,
+    /// 节点id（[PointData::index]）到其在force_point_data中下标的映射
+    index_map: HashMap<usize, usize>,
+    /// 下一个新增节点的id，id不会被复用
+    next_index: usize,
+    /// 新增节点的初始位置
+    initial_layout: InitialLayout<F, N>,
+    /// 全部物理力，按priority升序施加，priority相同时按加入顺序
+    forces: Vec<RegisteredForce<F, N, D>>,
     /// 每一时间刻，会降低alpha（根据alpha_decay和alpha_target），当alpha小于alpha_min，将停止物理模拟
     alpha: F,
     alpha_min: F,
@@ -24,110 +65,555 @@ pub struct Simulation<F: Float, const N: usize, D> {
     alpha_target: F,
     /// 每一时间刻，节点速度下降率
     velocity_decay: F,
+    /// 由速度更新节点位置的方式
+    integrator: Box<dyn Integrator<F, N, D>>,
+    /// 积分之后投影的几何约束
+    constraints: Vec<Constraint<F, N>>,
+    /// 每次tick依次投影全部约束的轮数
+    constraint_iterations: usize,
+    /// 在约束之后投影的硬边界
+    boundary: Option<(Boundary<F, N>, BoundaryMode<F>)>,
+    /// 本次tick之前的节点坐标，用于计算位移
+    previous_coords: Vec<[F; N]>,
+    /// alpha的衰减方式
+    cooling: Box<dyn CoolingSchedule<F>>,
+    last_tick: Option<TickStats<F>>,
+    /// 所有力共享的随机数生成器，固定seed后布局结果可复现
+    rng: StdRng,
+    /// 动能或最大位移低于阈值时同样视为收敛
+    energy_threshold: Option<F>,
+    displacement_threshold: Option<F>,
+    running: bool,
+    tick_listeners: Vec<TickListener<F>>,
+    end_listeners: Vec<EndListener>,
 }
 
 impl<'d, F: Float, const N: usize, D> Default for Simulation<F, N, D> {
     fn default() -> Self {
         Simulation {
             force_point_data: Vec::new(),
-            forces: HashMap::new(),
+            index_map: HashMap::new(),
+            next_index: 0,
+            initial_layout: InitialLayout::Phyllotaxis,
+            forces: Vec::new(),
             alpha: F::one(),
             alpha_min: F::from(0.001).unwrap(),
             alpha_decay: F::from(1.0 - 0.001.powf(1.0 / 300.0)).unwrap(),
             alpha_target: F::zero(),
             velocity_decay: F::from(0.6).unwrap(),
+            integrator: Box::new(EulerIntegrator),
+            constraints: Vec::new(),
+            constraint_iterations: 10,
+            boundary: None,
+            previous_coords: Vec::new(),
+            cooling: Box::new(ExponentialCooling),
+            last_tick: None,
+            rng: StdRng::from_entropy(),
+            energy_threshold: None,
+            displacement_threshold: None,
+            running: true,
+            tick_listeners: Vec::new(),
+            end_listeners: Vec::new(),
         }
     }
 }
 
-impl<F: Float, const N: usize, D> Simulation<F, N, D> {
+impl<F: Float + 'static, const N: usize, D: 'static> Simulation<F, N, D> {
     pub fn from_data(data: Vec<D>) -> Simulation<F, N, D> {
         let mut simulation = Simulation::default();
-        let nodes = Self::init_point_data(data);
-        simulation.force_point_data.extend(nodes);
+        simulation.set_data(data);
+        simulation
+    }
+
+    /// Create an empty simulation whose layout is reproducible for the given seed
+    pub fn with_seed(seed: u64) -> Simulation<F, N, D> {
+        let mut simulation = Simulation::default();
+        simulation.set_seed(seed);
         simulation
     }
 
+    /// Reseed the random number generator shared by all forces
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     /// Initialize [Simulation.force_point_data] through data
     /// if Simulation initialized by [Simulation::default()]
     pub fn set_data(&mut self, data: Vec<D>) {
-        let nodes = Self::init_point_data(data);
+        self.add_nodes(data);
+    }
+
+    /// Append nodes and re-initialise every force, returns the ids of the new nodes
+    ///
+    /// Existing nodes keep their id, position and velocity
+    pub fn add_nodes(&mut self, data: Vec<D>) -> Vec<usize> {
+        let start = self.next_index;
+        let mut nodes = Self::init_point_data(data, start);
+        if !matches!(self.initial_layout, InitialLayout::Phyllotaxis) {
+            self.initial_layout
+                .place(&mut nodes, &self.force_point_data);
+        }
+        self.next_index += nodes.len();
         self.force_point_data.extend(nodes);
+        self.reindex();
+        (start..self.next_index).collect()
+    }
+
+    /// Remove the nodes with the given ids and re-initialise every force, returns the removed nodes
+    ///
+    /// Unknown ids are ignored, the remaining nodes keep their id, position and velocity
+    pub fn remove_nodes(&mut self, ids: &[usize]) -> Vec<PointData<F, N, D>> {
+        let ids: HashSet<usize> = ids.iter().copied().collect();
+        let (removed, kept) = std::mem::take(&mut self.force_point_data)
+            .into_iter()
+            .partition(|point_data| ids.contains(&point_data.index));
+        self.force_point_data = kept;
+        self.reindex();
+        removed
     }
 
-    pub fn add_force(&mut self, name: String, mut force: Box<dyn ForceSimulate<F, N, D>>) {
-        force.init(&self.force_point_data);
-        self.forces.insert(name, force);
+    /// Placement of the nodes added from now on, existing nodes stay where they are
+    /// until [Simulation::reset_positions]
+    pub fn set_initial_layout(&mut self, initial_layout: InitialLayout<F, N>) {
+        self.initial_layout = initial_layout;
+    }
+
+    /// Move every node back to its initial position and clear its velocity
+    pub fn reset_positions(&mut self) {
+        self.initial_layout.place(&mut self.force_point_data, &[]);
+    }
+
+    /// Position of the node with id `index` in [Simulation.force_point_data]
+    pub fn position_of(&self, index: usize) -> Option<usize> {
+        self.index_map.get(&index).copied()
+    }
+
+    /// index_map与force_point_data中每个节点的id和下标一致
+    fn is_indexed(&self) -> bool {
+        self.index_map.len() == self.force_point_data.len()
+            && self
+                .force_point_data
+                .iter()
+                .enumerate()
+                .all(|(position, point_data)| {
+                    self.index_map.get(&point_data.index) == Some(&position)
+                })
+    }
+
+    fn reindex(&mut self) {
+        self.index_map = PointData::index_map(&self.force_point_data);
+        let context = ForceContext::new(&self.force_point_data, &self.index_map);
+        for registered in self.forces.iter_mut() {
+            registered.force.init(&context);
+        }
+    }
+
+    /// Register a force with priority 0, see [Simulation::add_force_with_priority]
+    pub fn add_force(&mut self, name: String, force: Box<dyn ForceSimulate<F, N, D>>) {
+        self.add_force_with_priority(name, 0, force);
+    }
+
+    /// Register a force, forces apply in ascending priority and then in insertion order
+    ///
+    /// A force registered under an existing name replaces it and is ordered as a new insertion
+    pub fn add_force_with_priority(
+        &mut self,
+        name: String,
+        priority: i32,
+        mut force: Box<dyn ForceSimulate<F, N, D>>,
+    ) {
+        self.remove_force(&name);
+        force.init(&ForceContext::new(&self.force_point_data, &self.index_map));
+        let position = self
+            .forces
+            .iter()
+            .position(|registered| registered.priority > priority)
+            .unwrap_or(self.forces.len());
+        self.forces.insert(
+            position,
+            RegisteredForce {
+                name,
+                priority,
+                force,
+            },
+        );
     }
 
     pub fn remove_force(&mut self, name: &str) -> Option<Box<dyn ForceSimulate<F, N, D>>> {
-        self.forces.remove(name)
+        let position = self
+            .forces
+            .iter()
+            .position(|registered| registered.name == name)?;
+        Some(self.forces.remove(position).force)
+    }
+
+    /// Names of the registered forces in the order they apply
+    pub fn force_names(&self) -> impl Iterator<Item = &str> {
+        self.forces
+            .iter()
+            .map(|registered| registered.name.as_str())
+    }
+
+    /// The force registered as `name`, None if there is none or it is not a `T`
+    pub fn get_force<T: ForceSimulate<F, N, D>>(&self, name: &str) -> Option<&T> {
+        let registered = self
+            .forces
+            .iter()
+            .find(|registered| registered.name == name)?;
+        (registered.force.as_ref() as &dyn Any).downcast_ref::<T>()
+    }
+
+    /// Mutable access to the force registered as `name` for reconfiguring it
+    pub fn get_force_mut<T: ForceSimulate<F, N, D>>(&mut self, name: &str) -> Option<&mut T> {
+        let registered = self
+            .forces
+            .iter_mut()
+            .find(|registered| registered.name == name)?;
+        (registered.force.as_mut() as &mut dyn Any).downcast_mut::<T>()
+    }
+
+    pub fn alpha(&self) -> F {
+        self.alpha
+    }
+
+    pub fn set_alpha(&mut self, alpha: F) {
+        self.alpha = alpha;
+    }
+
+    pub fn alpha_min(&self) -> F {
+        self.alpha_min
+    }
+
+    pub fn set_alpha_min(&mut self, alpha_min: F) {
+        self.alpha_min = alpha_min;
+    }
+
+    pub fn alpha_decay(&self) -> F {
+        self.alpha_decay
+    }
+
+    pub fn set_alpha_decay(&mut self, alpha_decay: F) {
+        self.alpha_decay = alpha_decay;
+    }
+
+    pub fn alpha_target(&self) -> F {
+        self.alpha_target
+    }
+
+    pub fn set_alpha_target(&mut self, alpha_target: F) {
+        self.alpha_target = alpha_target;
+    }
+
+    pub fn velocity_decay(&self) -> F {
+        self.velocity_decay
+    }
+
+    pub fn set_velocity_decay(&mut self, velocity_decay: F) {
+        self.velocity_decay = velocity_decay;
+    }
+
+    pub fn set_cooling_schedule(&mut self, cooling: Box<dyn CoolingSchedule<F>>) {
+        self.cooling = cooling;
+    }
+
+    pub fn set_integrator(&mut self, integrator: Box<dyn Integrator<F, N, D>>) {
+        self.integrator = integrator;
+    }
+
+    /// Fix every axis of the node with id `index` at `coord`, false if there is no such node
+    pub fn pin(&mut self, index: usize, coord: [F; N]) -> bool {
+        self.node_mut(index)
+            .map(|point_data| point_data.pin(coord))
+            .is_some()
+    }
+
+    /// Fix a single axis of the node with id `index`, the other axes keep moving
+    ///
+    /// Panics if `axis >= N`
+    pub fn pin_axis(&mut self, index: usize, axis: usize, value: F) -> bool {
+        self.node_mut(index)
+            .map(|point_data| point_data.fixed_position[axis] = Some(value))
+            .is_some()
+    }
+
+    /// Release every fixed axis and the anchor of the node with id `index`
+    pub fn unpin(&mut self, index: usize) -> bool {
+        self.node_mut(index)
+            .map(|point_data| point_data.unpin())
+            .is_some()
     }
 
-    pub fn tick(&mut self) {
-        self.alpha = self.alpha + (self.alpha_target - self.alpha) * self.alpha_decay;
+    /// Pull the node with id `index` toward `target` with a spring of `strength` every tick,
+    /// unlike pinning the node may be pushed away by the other forces
+    pub fn anchor(&mut self, index: usize, target: [F; N], strength: F) -> bool {
+        self.node_mut(index)
+            .map(|point_data| point_data.anchor = Some((target, strength)))
+            .is_some()
+    }
+
+    fn node_mut(&mut self, index: usize) -> Option<&mut PointData<F, N, D>> {
+        let position = self.position_of(index)?;
+        self.force_point_data.get_mut(position)
+    }
+
+    /// Add a constraint projected after integration in every tick, returns its index
+    pub fn add_constraint(&mut self, constraint: Constraint<F, N>) -> usize {
+        self.constraints.push(constraint);
+        self.constraints.len() - 1
+    }
+
+    /// Remove the constraint at `index`, the following constraints move down by one
+    pub fn remove_constraint(&mut self, index: usize) -> Option<Constraint<F, N>> {
+        (index < self.constraints.len()).then(|| self.constraints.remove(index))
+    }
+
+    pub fn constraints(&self) -> &[Constraint<F, N>] {
+        &self.constraints
+    }
+
+    pub fn clear_constraints(&mut self) {
+        self.constraints.clear();
+    }
+
+    /// Rounds of projecting every constraint per tick, more rounds satisfy conflicting
+    /// constraints more closely
+    pub fn set_constraint_iterations(&mut self, iterations: usize) {
+        self.constraint_iterations = iterations;
+    }
+
+    /// Keep every node inside `boundary`, projected after integration and the constraints in
+    /// every tick so it holds whatever the integrator and the other forces do
+    ///
+    /// See [crate::force::BoundaryForce] for a soft boundary
+    pub fn set_boundary(&mut self, boundary: Boundary<F, N>, mode: BoundaryMode<F>) {
+        self.boundary = Some((boundary, mode));
+    }
+
+    pub fn boundary(&self) -> Option<&(Boundary<F, N>, BoundaryMode<F>)> {
+        self.boundary.as_ref()
+    }
+
+    pub fn clear_boundary(&mut self) {
+        self.boundary = None;
+    }
+
+    /// Set alpha back to 1 and resume the simulation from a fresh cooling schedule and integrator
+    pub fn restart(&mut self) {
+        self.cooling.reset();
+        self.integrator.reset();
+        self.last_tick = None;
+        self.reheat(F::one());
+    }
+
+    /// Set alpha and resume the simulation if it has stopped
+    pub fn reheat(&mut self, alpha: F) {
+        self.alpha = alpha;
+        self.running = true;
+    }
+
+    /// Stop once the total kinetic energy of a tick falls below `threshold`
+    pub fn set_energy_threshold(&mut self, threshold: Option<F>) {
+        self.energy_threshold = threshold;
+    }
+
+    /// Stop once no node moves farther than `threshold` in a tick
+    pub fn set_displacement_threshold(&mut self, threshold: Option<F>) {
+        self.displacement_threshold = threshold;
+    }
+
+    /// False once a stop condition has been met by the last tick
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Register a callback invoked after every tick
+    pub fn on_tick(&mut self, listener: impl FnMut(&TickStats<F>) + 'static) {
+        self.tick_listeners.push(Box::new(listener));
+    }
+
+    /// Register a callback invoked when the simulation stops
+    pub fn on_end(&mut self, listener: impl FnMut(StopReason) + 'static) {
+        self.end_listeners.push(Box::new(listener));
+    }
 
-        for (_, force) in &self.forces {
-            force.force(&mut self.force_point_data, self.alpha)
+    /// Tick until a stop condition is met, at most `max_ticks` times
+    pub fn run_until_stable(&mut self, max_ticks: usize) -> StopReason {
+        for _ in 0..max_ticks {
+            let stats = self.tick();
+            if let Some(reason) = self.stop_reason(&stats) {
+                return reason;
+            }
         }
+        StopReason::MaxTicks
+    }
 
-        for point_data in &mut self.force_point_data {
-            match point_data.fixed_position {
-                None => {
-                    for i in 0..N {
-                        point_data.velocity[i] = point_data.velocity[i] * self.velocity_decay;
-                    }
-                    let v = point_data.velocity;
-                    let coord = point_data.coord_mut();
-                    for i in 0..N {
-                        coord[i] = coord[i] + v[i];
-                    }
-                }
-                Some(fixed_position) => {
-                    *point_data.coord_mut() = fixed_position;
-                    point_data.velocity = [F::zero(); N]
+    /// Iterate over the stats of each tick until a stop condition is met, at most `max_ticks` times
+    pub fn ticks(&mut self, max_ticks: usize) -> Ticks<'_, F, N, D> {
+        Ticks {
+            simulation: self,
+            remaining: max_ticks,
+        }
+    }
+
+    pub fn tick(&mut self) -> TickStats<F> {
+        self.alpha = self.cooling.next_alpha(
+            self.alpha,
+            self.alpha_target,
+            self.alpha_decay,
+            self.last_tick.as_ref(),
+        );
+
+        // force_point_data被直接修改过（增删、重排或替换节点）
+        if !self.is_indexed() {
+            self.reindex();
+        }
+        let context = ForceContext::new(&self.force_point_data, &self.index_map);
+        for registered in self.forces.iter_mut() {
+            if registered.force.needs_init() {
+                registered.force.init(&context);
+            }
+        }
+        self.integrator.before_forces(&self.force_point_data);
+        let mut context = TickContext::new(&mut self.force_point_data, self.alpha, &mut self.rng);
+        for registered in self.forces.iter_mut() {
+            registered.force.force(&mut context)
+        }
+        // 软锚点：不随alpha衰减的弹簧
+        for point_data in self.force_point_data.iter_mut() {
+            if let Some((target, strength)) = point_data.anchor {
+                for i in 0..N {
+                    point_data.velocity[i] =
+                        point_data.velocity[i] + (target[i] - point_data.coord[i]) * strength;
                 }
             }
         }
-    }
 
-    fn init_point_data(data: Vec<D>) -> Vec<PointData<F, N, D>> {
-        let mut nodes = Vec::with_capacity(data.len());
-        for (idx, datum) in data.into_iter().enumerate() {
-            let idx_f = idx as f64;
-            let radius = *INITIAL_RADIUS
-                * match N {
-                    1 => idx_f,
-                    2 => (0.5 + idx_f).sqrt(),
-                    3 => (0.5 + idx_f).cbrt(),
-                    _ => panic!("unsupported dim > 3 "),
-                };
-            let roll_angle = idx_f * *INITIAL_ANGLE_ROLL;
-            let yaw_angle = idx_f * *INITIAL_ANGLE_YAW;
-            let mut coord: [F; N] = [F::zero(); N];
-            match N {
-                1 => coord[0] = F::from(radius).unwrap(),
-                2 => {
-                    coord[0] = F::from(radius * roll_angle.cos()).unwrap();
-                    coord[1] = F::from(radius * roll_angle.sin()).unwrap()
+        self.previous_coords.clear();
+        self.previous_coords.extend(
+            self.force_point_data
+                .iter()
+                .map(|point_data| point_data.coord),
+        );
+        self.integrator
+            .integrate(&mut self.force_point_data, self.velocity_decay);
+
+        for point_data in self.force_point_data.iter_mut() {
+            for i in 0..N {
+                if let Some(fixed) = point_data.fixed_position[i] {
+                    point_data.coord[i] = fixed;
+                    point_data.velocity[i] = F::zero();
+                }
+            }
+        }
+        // 固定节点已复位，投影时不会移动
+        if !self.constraints.is_empty() {
+            for _ in 0..self.constraint_iterations {
+                for constraint in self.constraints.iter() {
+                    constraint.project(&mut self.force_point_data, &self.index_map);
                 }
-                3 => {
-                    coord[0] = F::from(radius * roll_angle.sin() * yaw_angle.cos()).unwrap();
-                    coord[1] = F::from(radius * roll_angle.cos()).unwrap();
-                    coord[2] = F::from(radius * roll_angle.sin() * yaw_angle.sin()).unwrap();
+            }
+        }
+        // 边界最后投影，约束无法同时满足时优先保证节点不越界
+        if let Some((boundary, mode)) = &self.boundary {
+            for point_data in self.force_point_data.iter_mut() {
+                boundary.project(point_data, *mode);
+            }
+        }
+
+        let mut kinetic_energy = F::zero();
+        let mut max_displacement = F::zero();
+        let half = F::from(0.5).unwrap();
+        for (point_data, previous) in self.force_point_data.iter().zip(&self.previous_coords) {
+            let square = point_data
+                .velocity
+                .iter()
+                .fold(F::zero(), |s, &x| s + x * x);
+            kinetic_energy = kinetic_energy + square * half;
+            let displacement = (0..N)
+                .map(|i| point_data.coord[i] - previous[i])
+                .fold(F::zero(), |s, x| s + x * x);
+            max_displacement = max_displacement.max(displacement.sqrt());
+        }
+
+        let stats = TickStats {
+            alpha: self.alpha,
+            kinetic_energy,
+            max_displacement,
+        };
+        self.last_tick = Some(stats);
+        for listener in self.tick_listeners.iter_mut() {
+            listener(&stats);
+        }
+        match self.stop_reason(&stats) {
+            Some(reason) if self.running => {
+                self.running = false;
+                for listener in self.end_listeners.iter_mut() {
+                    listener(reason);
                 }
-                _ => panic!("unsupported dim > 3 "),
-            };
-            nodes.push(PointData::from_data(datum, coord, idx))
+            }
+            Some(_) => {}
+            None => self.running = true,
+        }
+        stats
+    }
+
+    fn stop_reason(&self, stats: &TickStats<F>) -> Option<StopReason> {
+        if stats.alpha < self.alpha_min {
+            return Some(StopReason::AlphaMin);
+        }
+        if let Some(threshold) = self.energy_threshold {
+            if stats.kinetic_energy < threshold {
+                return Some(StopReason::KineticEnergy);
+            }
+        }
+        if let Some(threshold) = self.displacement_threshold {
+            if stats.max_displacement < threshold {
+                return Some(StopReason::Displacement);
+            }
         }
-        nodes
+        None
+    }
+
+    pub(crate) fn init_point_data(data: Vec<D>, start: usize) -> Vec<PointData<F, N, D>> {
+        let coords = phyllotaxis::<F, N>(start..start + data.len());
+        (start..)
+            .zip(data)
+            .zip(coords)
+            .map(|((idx, datum), coord)| PointData::from_data(datum, coord, idx))
+            .collect()
     }
 }
 
+/// Iterator returned by [Simulation::ticks]
+pub struct Ticks<'a, F: Float, const N: usize, D> {
+    simulation: &'a mut Simulation<F, N, D>,
+    remaining: usize,
+}
+
+impl<'a, F: Float + 'static, const N: usize, D: 'static> Iterator for Ticks<'a, F, N, D> {
+    type Item = TickStats<F>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 || !self.simulation.is_running() {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(self.simulation.tick())
+    }
+}
+
+#[cfg(test)]
 mod tests {
-    use crate::force::NBodyForce;
-    use crate::simulation::Simulation;
-    use generic_tree::Node;
+    use crate::constraint::Constraint;
+    use crate::cooling::{AdaptiveCooling, LinearCooling};
+    use crate::force::{
+        Boundary, BoundaryMode, CenterForce, ClusterForce, ForceContext, ForceSimulate, LinkForce,
+        NBodyForce, PositionForce, RadialForce, TickContext,
+    };
+    use crate::integrator::{AdaptiveIntegrator, EulerIntegrator, Integrator, VerletIntegrator};
+    use crate::simulation::{Simulation, StopReason};
+    use std::cell::Cell;
+    use std::rc::Rc;
     use std::time::Instant;
 
     #[test]
@@ -196,4 +682,561 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_constraints() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(Vec::from_iter(0..10));
+        simulation.add_force(
+            String::from("n-body"),
+            Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+        );
+        simulation.pin(7, [10.0, 42.0]);
+        simulation.add_constraint(Constraint::Separation {
+            axis: 0,
+            left: 1,
+            right: 0,
+            gap: 50.0,
+        });
+        simulation.add_constraint(Constraint::Alignment {
+            axis: 1,
+            nodes: vec![2, 3, 4, 7],
+        });
+        simulation.add_constraint(Constraint::Offset {
+            source: 5,
+            target: 6,
+            offset: [20.0, -5.0],
+        });
+        assert_eq!(simulation.constraints().len(), 3);
+        for _ in 0..100 {
+            simulation.tick();
+            let coord = |index: usize| simulation.force_point_data[index].coord;
+            assert!(coord(1)[0] + 50.0 <= coord(0)[0] + 1e-9);
+            // 对齐到固定节点
+            for index in [2, 3, 4, 7] {
+                assert!((coord(index)[1] - 42.0).abs() < 1e-9);
+            }
+            assert!((coord(6)[0] - coord(5)[0] - 20.0).abs() < 1e-9);
+            assert!((coord(6)[1] - coord(5)[1] + 5.0).abs() < 1e-9);
+            assert_eq!(coord(7), [10.0, 42.0]);
+        }
+        assert!(simulation.remove_constraint(0).is_some());
+        assert!(simulation.remove_constraint(2).is_none());
+    }
+
+    #[test]
+    fn test_pinning() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(Vec::from_iter(0..20));
+        simulation.add_force(
+            String::from("n-body"),
+            Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+        );
+        let start = simulation.force_point_data[0].coord;
+        assert!(simulation.pin_axis(0, 0, 5.0));
+        assert!(simulation.pin(1, [-20.0, 30.0]));
+        assert!(simulation.anchor(2, [100.0, 100.0], 0.5));
+        assert!(!simulation.pin(20, [0.0, 0.0]));
+        for _ in 0..300 {
+            simulation.tick();
+        }
+        let coord = |simulation: &Simulation<f64, 2, i32>, index: usize| {
+            simulation.force_point_data[index].coord
+        };
+        // 只固定了x轴，y轴仍然受力移动
+        assert_eq!(coord(&simulation, 0)[0], 5.0);
+        assert_ne!(coord(&simulation, 0)[1], start[1]);
+        assert_eq!(coord(&simulation, 1), [-20.0, 30.0]);
+        let [x, y] = coord(&simulation, 2);
+        assert!((x - 100.0).hypot(y - 100.0) < 5.0);
+
+        assert!(simulation.unpin(1));
+        simulation.restart();
+        simulation.tick();
+        assert_ne!(coord(&simulation, 1), [-20.0, 30.0]);
+        assert!(!simulation.force_point_data[1].is_fixed());
+    }
+
+    #[test]
+    fn test_nbody_force_3d() {
+        let mut simulation: Simulation<f64, 3, i32> = Simulation::from_data(Vec::from_iter(0..200));
+        simulation.add_force(
+            String::from("n-body"),
+            Box::new(NBodyForce::<f64, 3, 8, i32>::default()),
+        );
+        let spread = |simulation: &Simulation<f64, 3, i32>| {
+            simulation
+                .force_point_data
+                .iter()
+                .map(|point_data| point_data.coord.iter().map(|x| x * x).sum::<f64>())
+                .fold(0.0, f64::max)
+        };
+        let before = spread(&simulation);
+        for _ in 0..10 {
+            simulation.tick();
+        }
+        // 八叉树上的斥力使节点散开
+        assert!(spread(&simulation) > before);
+    }
+
+    #[test]
+    fn test_dimension_4() {
+        let mut simulation: Simulation<f64, 4, i32> = Simulation::from_data(Vec::from_iter(0..300));
+        // 初始位置互不重合，且离原点的距离随id增长
+        let norm = |coord: &[f64; 4]| coord.iter().map(|x| x * x).sum::<f64>().sqrt();
+        for pair in simulation.force_point_data.windows(2) {
+            assert!(norm(&pair[0].coord) < norm(&pair[1].coord));
+        }
+        for (i, a) in simulation.force_point_data.iter().enumerate() {
+            for b in simulation.force_point_data[i + 1..].iter() {
+                assert_ne!(a.coord, b.coord);
+            }
+        }
+
+        let links = (1..300).map(|i| (i / 2, i)).collect::<Vec<_>>();
+        let mut link_force: LinkForce<f64, 4, i32> = LinkForce::default();
+        link_force.set_links(links.clone());
+        simulation.add_force(String::from("link"), Box::new(link_force));
+        simulation.add_force(
+            String::from("n-body"),
+            Box::new(NBodyForce::<f64, 4, 16, i32>::default()),
+        );
+        simulation.add_force(String::from("center"), Box::new(CenterForce::default()));
+        simulation.add_force(
+            String::from("cluster"),
+            Box::new(ClusterForce::new(
+                |point_data, _| (point_data.data % 3) as usize,
+                |_, _| 0.05,
+                0.0,
+            )),
+        );
+        simulation.add_force(String::from("position"), Box::new(PositionForce::default()));
+        simulation.set_boundary(
+            Boundary::Sphere {
+                center: [0.0; 4],
+                radius: 1000.0,
+            },
+            BoundaryMode::Clamp,
+        );
+        for _ in 0..300 {
+            simulation.tick();
+        }
+        for point_data in simulation.force_point_data.iter() {
+            assert!(point_data.coord.iter().all(|x| x.is_finite()));
+            assert!(norm(&point_data.coord) <= 1000.0 + 1e-9);
+        }
+        // 连边的长度接近LinkForce默认的30
+        let mean = links
+            .iter()
+            .map(|&(s, t)| {
+                let (a, b) = (
+                    simulation.force_point_data[s].coord,
+                    simulation.force_point_data[t].coord,
+                );
+                norm(&[a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]])
+            })
+            .sum::<f64>()
+            / links.len() as f64;
+        assert!(mean > 10.0 && mean < 90.0, "{}", mean);
+    }
+
+    #[test]
+    fn test_parallel_nbody_force() {
+        let build = |parallel: bool| {
+            let mut nbody_force = NBodyForce::<f64, 2, 4, i32>::default();
+            nbody_force.parallel = parallel;
+            let mut simulation: Simulation<f64, 2, i32> =
+                Simulation::from_data(Vec::from_iter(0..2000));
+            simulation.add_force(String::from("n-body"), Box::new(nbody_force));
+            for _ in 0..5 {
+                simulation.tick();
+            }
+            simulation
+        };
+        let sequential = build(false);
+        let parallel = build(true);
+        for (a, b) in sequential
+            .force_point_data
+            .iter()
+            .zip(parallel.force_point_data.iter())
+        {
+            assert_eq!(a.coord, b.coord);
+            assert_eq!(a.velocity, b.velocity);
+        }
+    }
+
+    #[test]
+    fn test_seeded_simulation() {
+        let build = |parallel: bool| {
+            let mut simulation: Simulation<f64, 2, i32> = Simulation::with_seed(42);
+            simulation.set_data(Vec::from_iter(0..200));
+            // 让节点两两重合，强制触发jiggle
+            for (i, point_data) in simulation.force_point_data.iter_mut().enumerate() {
+                point_data.coord = [(i / 2) as f64, (i / 2 % 7) as f64];
+            }
+            let mut nbody_force = NBodyForce::<f64, 2, 4, i32>::default();
+            nbody_force.parallel = parallel;
+            simulation.add_force(String::from("n-body"), Box::new(nbody_force));
+            for _ in 0..10 {
+                simulation.tick();
+            }
+            simulation
+        };
+        let first = build(true);
+        for other in [build(true), build(false)] {
+            for (a, b) in first
+                .force_point_data
+                .iter()
+                .zip(other.force_point_data.iter())
+            {
+                assert_eq!(a.coord, b.coord);
+                assert_eq!(a.velocity, b.velocity);
+            }
+        }
+    }
+
+    #[test]
+    fn test_force_registry() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(vec![1, 2, 3]);
+        simulation.add_force(String::from("center"), Box::new(CenterForce::default()));
+        simulation.add_force_with_priority(
+            String::from("n-body"),
+            -1,
+            Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+        );
+        simulation.add_force(String::from("position"), Box::new(PositionForce::default()));
+        simulation.add_force_with_priority(
+            String::from("link"),
+            1,
+            Box::new(LinkForce::<f64, 2, i32>::default()),
+        );
+        assert_eq!(
+            simulation.force_names().collect::<Vec<_>>(),
+            vec!["n-body", "center", "position", "link"]
+        );
+        // 同名替换视为新的插入
+        simulation.add_force(String::from("center"), Box::new(CenterForce::default()));
+        assert_eq!(
+            simulation.force_names().collect::<Vec<_>>(),
+            vec!["n-body", "position", "center", "link"]
+        );
+
+        assert!(simulation
+            .get_force::<CenterForce<f64, 2>>("n-body")
+            .is_none());
+        assert!(simulation
+            .get_force::<CenterForce<f64, 2>>("missing")
+            .is_none());
+        let nbody_force = simulation
+            .get_force_mut::<NBodyForce<f64, 2, 4, i32>>("n-body")
+            .unwrap();
+        nbody_force.theta = 0.5;
+        assert_eq!(
+            simulation
+                .get_force::<NBodyForce<f64, 2, 4, i32>>("n-body")
+                .unwrap()
+                .theta,
+            0.5
+        );
+        assert!(simulation.remove_force("position").is_some());
+        assert!(simulation.remove_force("position").is_none());
+        assert_eq!(
+            simulation.force_names().collect::<Vec<_>>(),
+            vec!["n-body", "center", "link"]
+        );
+    }
+
+    #[test]
+    fn test_reconfigure_force() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(vec![1, 2, 3]);
+        simulation.add_force(String::from("position"), Box::new(PositionForce::default()));
+        // 加入大量节点使force_point_data重新分配
+        simulation.add_nodes(Vec::from_iter(0..1000));
+        simulation
+            .get_force_mut::<PositionForce<f64, 2, i32>>("position")
+            .unwrap()
+            .set_target_positions(vec![[Some(50.0), None]; 1003]);
+        simulation.set_alpha_decay(0.0);
+        for _ in 0..200 {
+            simulation.tick();
+        }
+        for point_data in simulation.force_point_data.iter() {
+            assert!((point_data.coord[0] - 50.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_reconfigure_links() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(vec![0, 1, 2]);
+        let mut link_force: LinkForce<f64, 2, i32> = LinkForce::default();
+        link_force.set_links(vec![(0, 1)]);
+        simulation.add_force(String::from("link"), Box::new(link_force));
+        for (point_data, x) in simulation
+            .force_point_data
+            .iter_mut()
+            .zip([-100.0, 0.0, 100.0])
+        {
+            point_data.coord = [x, 0.0];
+        }
+        // 注册后替换全部边，下一次tick前重新init
+        simulation
+            .get_force_mut::<LinkForce<f64, 2, i32>>("link")
+            .unwrap()
+            .set_links(vec![(1, 2)]);
+        simulation.tick();
+        let link_force = simulation
+            .get_force::<LinkForce<f64, 2, i32>>("link")
+            .unwrap();
+        assert_eq!(link_force.count(), &[0, 1, 1]);
+        assert_eq!(simulation.force_point_data[0].coord, [-100.0, 0.0]);
+        assert!(simulation.force_point_data[2].coord[0] < 100.0);
+    }
+
+    #[test]
+    fn test_stateful_force() {
+        /// 统计每个tick的平均速度
+        #[derive(Default)]
+        struct SpeedRecorder {
+            speeds: Vec<f64>,
+        }
+
+        impl ForceSimulate<f64, 2, i32> for SpeedRecorder {
+            fn init(&mut self, _: &ForceContext<f64, 2, i32>) {}
+
+            fn force(&mut self, context: &mut TickContext<f64, 2, i32>) {
+                let sum = context
+                    .point_data
+                    .iter()
+                    .map(|point_data| point_data.velocity[0].hypot(point_data.velocity[1]))
+                    .sum::<f64>();
+                self.speeds.push(sum / context.point_data.len() as f64);
+            }
+        }
+
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(Vec::from_iter(0..50));
+        simulation.add_force(
+            String::from("n-body"),
+            Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+        );
+        simulation.add_force_with_priority(
+            String::from("recorder"),
+            1,
+            Box::new(SpeedRecorder::default()),
+        );
+        for _ in 0..10 {
+            simulation.tick();
+        }
+        let recorder = simulation.get_force::<SpeedRecorder>("recorder").unwrap();
+        assert_eq!(recorder.speeds.len(), 10);
+        assert!(recorder.speeds.iter().all(|&speed| speed > 0.0));
+    }
+
+    #[test]
+    fn test_deterministic_force_pipeline() {
+        let build = || {
+            let mut simulation: Simulation<f64, 2, i32> = Simulation::with_seed(7);
+            simulation.set_data(Vec::from_iter(0..100));
+            let mut link_force = LinkForce::<f64, 2, i32>::default();
+            link_force.set_links((1..100).map(|i| (i / 3, i)).collect());
+            simulation.add_force(String::from("link"), Box::new(link_force));
+            simulation.add_force(
+                String::from("n-body"),
+                Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+            );
+            simulation.add_force(String::from("position"), Box::new(PositionForce::default()));
+            simulation.add_force(String::from("center"), Box::new(CenterForce::default()));
+            for _ in 0..20 {
+                simulation.tick();
+            }
+            simulation
+        };
+        let first = build();
+        for _ in 0..3 {
+            let other = build();
+            for (a, b) in first
+                .force_point_data
+                .iter()
+                .zip(other.force_point_data.iter())
+            {
+                assert_eq!(a.coord, b.coord);
+                assert_eq!(a.velocity, b.velocity);
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_until_stable() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::with_seed(1);
+        simulation.set_data(Vec::from_iter(0..50));
+        simulation.add_force(
+            String::from("n-body"),
+            Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+        );
+        let ticks = Rc::new(Cell::new(0));
+        let ends = Rc::new(Cell::new(0));
+        let (ticks_ref, ends_ref) = (ticks.clone(), ends.clone());
+        simulation.on_tick(move |_| ticks_ref.set(ticks_ref.get() + 1));
+        simulation.on_end(move |reason| {
+            assert_eq!(reason, StopReason::AlphaMin);
+            ends_ref.set(ends_ref.get() + 1)
+        });
+        assert_eq!(simulation.run_until_stable(10), StopReason::MaxTicks);
+        assert!(simulation.is_running());
+        assert_eq!(simulation.run_until_stable(1000), StopReason::AlphaMin);
+        assert!(!simulation.is_running());
+        assert_eq!(ticks.get(), 300);
+        assert_eq!(ends.get(), 1);
+        assert_eq!(simulation.ticks(10).count(), 0);
+
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::with_seed(1);
+        simulation.set_data(Vec::from_iter(0..50));
+        simulation.add_force(
+            String::from("n-body"),
+            Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+        );
+        simulation.set_energy_threshold(Some(1.0));
+        let stats = simulation.ticks(1000).collect::<Vec<_>>();
+        assert!(stats.len() < 300);
+        assert!(stats.last().unwrap().kinetic_energy < 1.0);
+        assert!(!simulation.is_running());
+    }
+
+    #[test]
+    fn test_integrators() {
+        let energy = |integrator: Box<dyn Integrator<f64, 2, i32>>| {
+            let mut simulation: Simulation<f64, 2, i32> = Simulation::with_seed(3);
+            simulation.set_data(Vec::from_iter(0..300));
+            let mut link_force = LinkForce::<f64, 2, i32>::default();
+            link_force.set_links((1..300).map(|i| (i / 4, i)).collect());
+            simulation.add_force(String::from("link"), Box::new(link_force));
+            simulation.add_force(
+                String::from("n-body"),
+                Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+            );
+            simulation.set_integrator(integrator);
+            // 低阻尼下显式欧拉容易振荡
+            simulation.set_velocity_decay(0.9);
+            let stats = simulation.ticks(300).last().unwrap();
+            assert!(simulation
+                .force_point_data
+                .iter()
+                .all(|point_data| point_data.coord.iter().all(|x| x.is_finite())));
+            stats.kinetic_energy
+        };
+        let euler = energy(Box::<EulerIntegrator>::default());
+        assert!(energy(Box::<VerletIntegrator<f64, 2>>::default()) < euler);
+        assert!(energy(Box::<AdaptiveIntegrator<f64, 2>>::default()) < euler);
+    }
+
+    #[test]
+    fn test_cooling_schedule() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(vec![1, 2, 3]);
+        simulation.set_cooling_schedule(Box::new(LinearCooling::new(0.1)));
+        assert_eq!(simulation.run_until_stable(100), StopReason::AlphaMin);
+        assert!(simulation.alpha() < 1e-12);
+
+        simulation.reheat(0.5);
+        assert!(simulation.is_running());
+        simulation.tick();
+        assert!((simulation.alpha() - 0.4).abs() < 1e-12);
+
+        simulation.set_cooling_schedule(Box::new(AdaptiveCooling::default()));
+        simulation.set_alpha_target(0.3);
+        simulation.restart();
+        for _ in 0..1000 {
+            simulation.tick();
+        }
+        assert!(simulation.is_running());
+        assert!((simulation.alpha() - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_add_remove_nodes() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::with_seed(3);
+        assert_eq!(
+            simulation.add_nodes(Vec::from_iter(0..10)),
+            Vec::from_iter(0..10)
+        );
+        let mut link_force: LinkForce<f64, 2, i32> = LinkForce::default();
+        link_force.set_links(vec![(0, 1), (1, 2), (2, 3), (3, 12)]);
+        simulation.add_force(String::from("link"), Box::new(link_force));
+        simulation.add_force(
+            String::from("n-body"),
+            Box::new(NBodyForce::<f64, 2, 4, i32>::default()),
+        );
+        simulation.add_force(String::from("position"), Box::new(PositionForce::default()));
+        for _ in 0..10 {
+            simulation.tick();
+        }
+
+        let before = simulation.force_point_data[5].clone();
+        // (3, 12)在节点12加入后生效
+        assert_eq!(simulation.add_nodes(vec![10, 11, 12]), vec![10, 11, 12]);
+        assert_eq!(simulation.force_point_data[5].coord, before.coord);
+        assert_eq!(simulation.force_point_data[5].velocity, before.velocity);
+
+        let removed = simulation.remove_nodes(&[2, 7, 100]);
+        assert_eq!(
+            removed.iter().map(|d| d.index).collect::<Vec<_>>(),
+            vec![2, 7]
+        );
+        assert_eq!(simulation.force_point_data.len(), 11);
+        assert_eq!(simulation.position_of(5), Some(4));
+        assert_eq!(simulation.position_of(12), Some(10));
+        assert_eq!(simulation.position_of(7), None);
+        assert_eq!(simulation.force_point_data[4].coord, before.coord);
+
+        assert_eq!(simulation.add_nodes(vec![13]), vec![13]);
+        for _ in 0..10 {
+            simulation.tick();
+        }
+        for point_data in simulation.force_point_data.iter() {
+            assert!(point_data.coord.iter().all(|x| x.is_finite()));
+        }
+    }
+
+    #[test]
+    fn test_per_index_values_after_add_nodes() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(vec![0, 1, 2]);
+        let mut nbody_force = NBodyForce::<f64, 2, 4, i32>::default();
+        nbody_force.set_strengths(vec![-10.0, -20.0, -30.0]);
+        let mut radial_force = RadialForce::default();
+        radial_force.set_radiuses(vec![10.0, 20.0, 30.0]);
+        radial_force.set_strengths(vec![0.1, 0.2, 0.3]);
+        let mut position_force = PositionForce::default();
+        position_force.set_target_positions(vec![[Some(1.0), None]; 3]);
+        position_force.set_strengths(vec![[Some(0.1), None]; 3]);
+        simulation.add_force(String::from("n-body"), Box::new(nbody_force));
+        simulation.add_force(String::from("radial"), Box::new(radial_force));
+        simulation.add_force(String::from("position"), Box::new(position_force));
+        simulation.tick();
+
+        // 新节点超出按下标给定的值，使用默认值
+        simulation.add_nodes(vec![3, 4]);
+        for _ in 0..10 {
+            simulation.tick();
+        }
+        for point_data in simulation.force_point_data.iter() {
+            assert!(point_data.coord.iter().all(|x| x.is_finite()));
+        }
+    }
+
+    #[test]
+    fn test_reorder_nodes() {
+        let mut simulation: Simulation<f64, 2, i32> = Simulation::from_data(vec![0, 1, 2]);
+        let mut link_force: LinkForce<f64, 2, i32> = LinkForce::default();
+        link_force.set_links(vec![(0, 1)]);
+        link_force.set_distance(10.0);
+        simulation.add_force(String::from("link"), Box::new(link_force));
+        simulation.force_point_data[0].coord = [-100.0, 0.0];
+        simulation.force_point_data[1].coord = [100.0, 0.0];
+        simulation.force_point_data[2].coord = [0.0, 50.0];
+        // 节点数不变，只有顺序变化
+        simulation.force_point_data.reverse();
+        simulation.tick();
+        assert_eq!(simulation.position_of(0), Some(2));
+        let coord =
+            |id: usize| simulation.force_point_data[simulation.position_of(id).unwrap()].coord;
+        assert!(coord(0)[0] > -100.0);
+        assert!(coord(1)[0] < 100.0);
+        assert_eq!(coord(2), [0.0, 50.0]);
+    }
 }
@@ -1,13 +1,18 @@
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 
 use simulation::{
     force::{CenterForce, LinkForce, NBodyForce, PositionForce},
+    metrics::LayoutMetrics,
     Simulation,
 };
 use wasm_bindgen::prelude::*;
 
+/// 拖拽期间的alpha_target，与d3的示例一致
+pub(crate) const DRAG_ALPHA_TARGET: f64 = 0.3;
+
 #[derive(Clone)]
-struct RandomData {
+pub(crate) struct RandomData {
     data: Vec<i32>,
 }
 
@@ -23,10 +28,47 @@ impl Display for RandomData {
     }
 }
 
+/// Layout quality returned by [ForceGraph2D::get_metrics]
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct Metrics {
+    pub stress: f64,
+    pub edge_length_variance: f64,
+    /// `undefined` when crossings are not defined, as for [crate::ForceGraph3D]
+    pub edge_crossings: Option<u32>,
+    pub min_separation: f64,
+    pub overlaps: usize,
+    pub kinetic_energy: f64,
+}
+
+impl From<LayoutMetrics<f64>> for Metrics {
+    fn from(metrics: LayoutMetrics<f64>) -> Self {
+        Metrics {
+            stress: metrics.stress,
+            edge_length_variance: metrics.edge_length_variance,
+            edge_crossings: metrics.edge_crossings.map(|crossings| crossings as u32),
+            min_separation: metrics.min_separation,
+            overlaps: metrics.overlaps,
+            kinetic_energy: metrics.kinetic_energy,
+        }
+    }
+}
+
+/// `links_data` holds the source and target of every link one after another
+pub(crate) fn links_from_flat(links_data: &[usize]) -> Vec<(usize, usize)> {
+    let mut links = Vec::new();
+    for i in (0..links_data.len()).step_by(2) {
+        links.push((links_data[i], links_data[i + 1]));
+    }
+    links
+}
+
 #[wasm_bindgen]
 pub struct ForceGraph2D {
     node_positions: Vec<(f64, f64)>,
     simulation: Simulation<f64, 2, RandomData>,
+    /// 正在被拖拽的节点
+    dragging: HashSet<usize>,
 }
 
 #[wasm_bindgen]
@@ -49,23 +91,33 @@ impl ForceGraph2D {
     // }
 
     pub fn build_graph(node_num: usize, links_data: &[usize]) -> Self {
+        Self::from_simulation(Simulation::default(), node_num, links_data)
+    }
+
+    /// Same as [ForceGraph2D::build_graph], but the layout is reproducible for the given seed
+    pub fn build_graph_with_seed(node_num: usize, links_data: &[usize], seed: u64) -> Self {
+        Self::from_simulation(Simulation::with_seed(seed), node_num, links_data)
+    }
+
+    fn from_simulation(
+        mut simulation: Simulation<f64, 2, RandomData>,
+        node_num: usize,
+        links_data: &[usize],
+    ) -> Self {
         let mut data = Vec::with_capacity(node_num);
         for _ in 0..node_num {
             data.push(RandomData::default())
         }
         let node_positions = vec![(0., 0.); node_num];
-        let mut simulation: Simulation<f64, 2, RandomData> = Simulation::from_data(data);
-        let mut link_force = LinkForce::default();
-        let mut links = Vec::new();
-        for i in (0..links_data.len()).step_by(2) {
-            links.push((links_data[i], links_data[i + 1]));
-        }
-        link_force.set_links(links);
+        simulation.set_data(data);
+        let mut link_force: LinkForce<f64, 2, RandomData> = LinkForce::default();
+        link_force.set_links(links_from_flat(links_data));
         simulation.add_force(String::from("link"), Box::new(link_force));
 
         let mut out = ForceGraph2D {
             node_positions,
             simulation,
+            dragging: HashSet::new(),
         };
 
         out.tick(1, false);
@@ -75,7 +127,7 @@ impl ForceGraph2D {
     pub fn add_n_body_force(&mut self) {
         let mut nbody_force: NBodyForce<f64, 2, 4, RandomData> = NBodyForce::default();
         nbody_force.distance_min = 10_f64;
-        nbody_force.set_strength_fn(|_, _| -1_f64);
+        nbody_force.set_strength(-1_f64);
         self.simulation
             .add_force(String::from("official:n-body"), Box::new(nbody_force));
     }
@@ -107,4 +159,72 @@ impl ForceGraph2D {
     pub fn get_pos(&self) -> *const (f64, f64) {
         self.node_positions.as_ptr()
     }
+
+    /// Fix node `index` at (`x`, `y`), returns false if there is no such node
+    pub fn pin(&mut self, index: usize, x: f64, y: f64) -> bool {
+        self.simulation.pin(index, [x, y])
+    }
+
+    /// Fix only one axis of node `index`, 0 for x (fx) and 1 for y (fy)
+    pub fn pin_axis(&mut self, index: usize, axis: usize, value: f64) -> bool {
+        axis < 2 && self.simulation.pin_axis(index, axis, value)
+    }
+
+    /// Release the fixed axes and the anchor of node `index`
+    pub fn unpin(&mut self, index: usize) -> bool {
+        self.simulation.unpin(index)
+    }
+
+    /// Pull node `index` toward (`x`, `y`) with a spring of `strength`
+    pub fn anchor(&mut self, index: usize, x: f64, y: f64, strength: f64) -> bool {
+        self.simulation.anchor(index, [x, y], strength)
+    }
+
+    /// Pin `node` where it is and keep the simulation warm while it is dragged,
+    /// returns false if there is no such node
+    pub fn drag_start(&mut self, node: usize) -> bool {
+        let coord = match self.simulation.position_of(node) {
+            Some(position) => self.simulation.force_point_data[position].coord,
+            None => return false,
+        };
+        // 第一个节点开始拖拽时升温，之后的拖拽沿用
+        if self.dragging.is_empty() {
+            self.simulation.set_alpha_target(DRAG_ALPHA_TARGET);
+            self.simulation.reheat(self.simulation.alpha());
+        }
+        self.dragging.insert(node);
+        self.simulation.pin(node, coord)
+    }
+
+    /// Move the dragged `node` to (`x`, `y`), the other nodes react on the following ticks
+    pub fn drag_move(&mut self, node: usize, x: f64, y: f64) -> bool {
+        let position = match self.simulation.position_of(node) {
+            Some(position) if self.dragging.contains(&node) => position,
+            _ => return false,
+        };
+        self.simulation.force_point_data[position].coord = [x, y];
+        self.node_positions[position] = (x, y);
+        self.simulation.pin(node, [x, y])
+    }
+
+    /// Release `node`, the simulation cools down again once no node is dragged
+    pub fn drag_end(&mut self, node: usize) -> bool {
+        if !self.dragging.remove(&node) {
+            return false;
+        }
+        if self.dragging.is_empty() {
+            self.simulation.set_alpha_target(0.0);
+        }
+        self.simulation.unpin(node)
+    }
+
+    /// Quality of the current layout, `edge_length` is the desired length of a link
+    pub fn get_metrics(&self, edge_length: f64) -> Metrics {
+        let links = self
+            .simulation
+            .get_force::<LinkForce<f64, 2, RandomData>>("link")
+            .map(|link_force| link_force.links.as_slice())
+            .unwrap_or(&[]);
+        LayoutMetrics::from_simulation(&self.simulation, links, edge_length).into()
+    }
 }
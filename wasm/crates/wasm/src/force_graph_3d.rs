@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+
+use simulation::{
+    force::{CenterForce, LinkForce, NBodyForce},
+    metrics::LayoutMetrics,
+    Simulation,
+};
+use wasm_bindgen::prelude::*;
+
+use crate::force_graph::{links_from_flat, Metrics, RandomData, DRAG_ALPHA_TARGET};
+
+/// 3-D counterpart of [crate::ForceGraph2D], positions are interleaved as x, y, z
+#[wasm_bindgen]
+pub struct ForceGraph3D {
+    node_positions: Vec<f64>,
+    simulation: Simulation<f64, 3, RandomData>,
+    /// 正在被拖拽的节点
+    dragging: HashSet<usize>,
+}
+
+#[wasm_bindgen]
+impl ForceGraph3D {
+    pub fn build_graph(node_num: usize, links_data: &[usize]) -> Self {
+        Self::from_simulation(Simulation::default(), node_num, links_data)
+    }
+
+    /// Same as [ForceGraph3D::build_graph], but the layout is reproducible for the given seed
+    pub fn build_graph_with_seed(node_num: usize, links_data: &[usize], seed: u64) -> Self {
+        Self::from_simulation(Simulation::with_seed(seed), node_num, links_data)
+    }
+
+    fn from_simulation(
+        mut simulation: Simulation<f64, 3, RandomData>,
+        node_num: usize,
+        links_data: &[usize],
+    ) -> Self {
+        let mut data = Vec::with_capacity(node_num);
+        for _ in 0..node_num {
+            data.push(RandomData::default())
+        }
+        let node_positions = vec![0.; node_num * 3];
+        simulation.set_data(data);
+        let mut link_force: LinkForce<f64, 3, RandomData> = LinkForce::default();
+        link_force.set_links(links_from_flat(links_data));
+        simulation.add_force(String::from("link"), Box::new(link_force));
+
+        let mut out = ForceGraph3D {
+            node_positions,
+            simulation,
+            dragging: HashSet::new(),
+        };
+
+        out.tick(1, false);
+        out
+    }
+
+    pub fn add_n_body_force(&mut self) {
+        let mut nbody_force: NBodyForce<f64, 3, 8, RandomData> = NBodyForce::default();
+        nbody_force.distance_min = 10_f64;
+        nbody_force.set_strength(-1_f64);
+        self.simulation
+            .add_force(String::from("official:n-body"), Box::new(nbody_force));
+    }
+
+    pub fn add_center_force(&mut self) {
+        self.simulation.add_force(
+            String::from("official:center-force"),
+            Box::new(CenterForce::default()),
+        );
+    }
+
+    pub fn tick(&mut self, times: usize, changed: bool) {
+        if changed {
+            for (i, point) in self.simulation.force_point_data.iter_mut().enumerate() {
+                point
+                    .coord
+                    .copy_from_slice(&self.node_positions[i * 3..i * 3 + 3]);
+            }
+        }
+
+        for _ in 0..times {
+            self.simulation.tick();
+        }
+
+        for (i, point) in self.simulation.force_point_data.iter().enumerate() {
+            self.node_positions[i * 3..i * 3 + 3].copy_from_slice(&point.coord);
+        }
+    }
+
+    /// Pointer to `3 * node_num` floats, x, y and z of every node one after another
+    pub fn get_pos(&self) -> *const f64 {
+        self.node_positions.as_ptr()
+    }
+
+    /// Fix node `index` at (`x`, `y`, `z`), returns false if there is no such node
+    pub fn pin(&mut self, index: usize, x: f64, y: f64, z: f64) -> bool {
+        self.simulation.pin(index, [x, y, z])
+    }
+
+    /// Fix only one axis of node `index`, 0 for x, 1 for y and 2 for z
+    pub fn pin_axis(&mut self, index: usize, axis: usize, value: f64) -> bool {
+        axis < 3 && self.simulation.pin_axis(index, axis, value)
+    }
+
+    /// Release the fixed axes and the anchor of node `index`
+    pub fn unpin(&mut self, index: usize) -> bool {
+        self.simulation.unpin(index)
+    }
+
+    /// Pull node `index` toward (`x`, `y`, `z`) with a spring of `strength`
+    pub fn anchor(&mut self, index: usize, x: f64, y: f64, z: f64, strength: f64) -> bool {
+        self.simulation.anchor(index, [x, y, z], strength)
+    }
+
+    /// See [crate::ForceGraph2D::drag_start]
+    pub fn drag_start(&mut self, node: usize) -> bool {
+        let coord = match self.simulation.position_of(node) {
+            Some(position) => self.simulation.force_point_data[position].coord,
+            None => return false,
+        };
+        if self.dragging.is_empty() {
+            self.simulation.set_alpha_target(DRAG_ALPHA_TARGET);
+            self.simulation.reheat(self.simulation.alpha());
+        }
+        self.dragging.insert(node);
+        self.simulation.pin(node, coord)
+    }
+
+    /// Move the dragged `node` to (`x`, `y`, `z`), the other nodes react on the following ticks
+    pub fn drag_move(&mut self, node: usize, x: f64, y: f64, z: f64) -> bool {
+        let position = match self.simulation.position_of(node) {
+            Some(position) if self.dragging.contains(&node) => position,
+            _ => return false,
+        };
+        self.simulation.force_point_data[position].coord = [x, y, z];
+        self.node_positions[position * 3..position * 3 + 3].copy_from_slice(&[x, y, z]);
+        self.simulation.pin(node, [x, y, z])
+    }
+
+    /// See [crate::ForceGraph2D::drag_end]
+    pub fn drag_end(&mut self, node: usize) -> bool {
+        if !self.dragging.remove(&node) {
+            return false;
+        }
+        if self.dragging.is_empty() {
+            self.simulation.set_alpha_target(0.0);
+        }
+        self.simulation.unpin(node)
+    }
+
+    /// Quality of the current layout, [Metrics::edge_crossings] is undefined in 3-D
+    pub fn get_metrics(&self, edge_length: f64) -> Metrics {
+        let links = self
+            .simulation
+            .get_force::<LinkForce<f64, 3, RandomData>>("link")
+            .map(|link_force| link_force.links.as_slice())
+            .unwrap_or(&[]);
+        LayoutMetrics::from_simulation(&self.simulation, links, edge_length).into()
+    }
+}
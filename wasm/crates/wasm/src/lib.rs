@@ -2,8 +2,10 @@ extern crate generic_tree;
 extern crate simulation;
 extern crate wasm_bindgen;
 mod force_graph;
+mod force_graph_3d;
 use bumpalo_herd::Herd;
-pub use force_graph::ForceGraph2D;
+pub use force_graph::{ForceGraph2D, Metrics};
+pub use force_graph_3d::ForceGraph3D;
 use generic_tree::{parallel, Node, TreeData};
 use rayon::prelude::*;
 use wasm_bindgen::prelude::*;